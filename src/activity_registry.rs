@@ -0,0 +1,411 @@
+use std::fs::File;
+use std::io::prelude::*;
+use std::collections::{HashMap, HashSet};
+use json::JsonValue;
+
+use crate::encryption::{self, Cipher};
+use crate::tracker_data::TrackerData;
+
+// The separator between the category and the rest of a hierarchical activity name, such as "work/meetings"
+pub const CATEGORY_SEPARATOR: char = '/';
+
+// Struct Definition
+// Like the data file, the activities file is encrypted again when saved if it was encrypted when loaded
+#[derive(Clone)]
+pub struct ActivityRegistry {
+    activities: HashSet<String>,
+    aliases: HashMap<String, String>,
+    tags: HashMap<String, String>,
+    cipher: Option<Cipher>,
+}
+
+// Public Methods
+impl ActivityRegistry {
+    // Creates a new, empty ActivityRegistry
+    pub fn new() -> ActivityRegistry {
+        ActivityRegistry {
+            activities: HashSet::new(),
+            aliases: HashMap::new(),
+            tags: HashMap::new(),
+            cipher: None,
+        }
+    }
+
+    // Loads JSON formatted data from file filename into this ActivityRegistry
+    pub fn load_from_file(self: &mut Self, filename: &str) -> Result<(), String> {
+        // Open the file and get the contents
+        let mut contents = String::new();
+        if let Ok(mut file) = File::open(filename) {
+            match file.read_to_string(&mut contents) {
+                Ok(_) => {}
+                Err(_) => {return Err(format!("Load from file error: cannot read \"{filename}\""));}
+            }
+        }
+        // Decrypt the contents if the file is encrypted (with the current cipher if it has the same key, and keeping the
+        // cipher of the file), then parse them and use them to populate this ActivityRegistry
+        // A missing or plaintext file keeps the current cipher, so the file of an encrypted profile is encrypted when saved
+        let (contents, cipher) = encryption::decrypt_if_encrypted(&contents, &self.cipher, filename)?;
+        if cipher.is_some() {
+            self.cipher = cipher;
+        }
+        if !contents.is_empty() {
+            match json::parse(&contents) {
+                Ok(parsed) => self.from_json(&parsed)?,
+                Err(_) => {
+                    return Err(format!("Load from file error: cannot parse contents of \"{filename}\""));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Saves JSON formatted data from this ActivityRegistry into the file filename
+    pub fn save_to_file(self: &Self, filename: &str) -> Result<(), String> {
+        match File::create(filename) {
+            Ok(mut file) => {
+                match file.write_all(encryption::encrypt_if(&self.cipher, self.to_json().pretty(4))?.as_bytes()) {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err(format!("Save to file error: cannot write to \"{filename}\"")),
+                }
+            }
+            Err(_) => Err(format!("Save to file error: cannot open \"{filename}\"")),
+        }
+    }
+
+    // Changes how this ActivityRegistry is encrypted when saved (None saves it unencrypted)
+    pub fn set_cipher(self: &mut Self, cipher: Option<Cipher>) {
+        self.cipher = cipher;
+    }
+
+    // Registers every activity that has time recorded in the tracker data
+    pub fn register_from(self: &mut Self, tracker_data: &TrackerData) {
+        for activity in tracker_data.activity_usage().into_keys() {
+            self.activities.insert(activity);
+        }
+    }
+
+    // Registers a new activity
+    pub fn register(self: &mut Self, activity: &str) -> Result<(), String> {
+        if self.aliases.contains_key(activity) {
+            return Err(format!("Activity error: \"{}\" is already an alias for \"{}\"", activity, self.aliases[activity]));
+        }
+        self.activities.insert(activity.to_string());
+        Ok(())
+    }
+
+    // Returns whether an activity (or an alias for one) is known
+    pub fn is_known(self: &Self, name: &str) -> bool {
+        self.activities.contains(name) || self.aliases.contains_key(name)
+    }
+
+    // Returns every known activity and alias, in alphabetical order
+    pub fn names(self: &Self) -> Vec<String> {
+        let mut names: Vec<String> = self.activities.iter().chain(self.aliases.keys()).cloned().collect();
+        names.sort();
+        names
+    }
+
+    // Returns the activity a name refers to (the name itself if it is not an alias)
+    pub fn resolve(self: &Self, name: &str) -> String {
+        match self.aliases.get(name) {
+            Some(activity) => activity.clone(),
+            None => name.to_string(),
+        }
+    }
+
+    // Returns the activity a name refers to, registering it if allow_new is set
+    // Unknown names are rejected with suggestions of similar known names
+    pub fn check(self: &mut Self, name: &str, allow_new: bool) -> Result<String, String> {
+        if self.is_known(name) {
+            Ok(self.resolve(name))
+        } else if allow_new {
+            self.register(name)?;
+            Ok(name.to_string())
+        } else {
+            let suggestions = self.suggestions(name);
+            if suggestions.is_empty() {
+                Err(format!("Activity error: unknown activity \"{}\" (use \"--new\" to create it)", name))
+            } else {
+                let quoted: Vec<String> = suggestions.iter().map(|s| format!("\"{}\"", s)).collect();
+                Err(format!("Activity error: unknown activity \"{}\", did you mean {}? (use \"--new\" to create it)", name, quoted.join(" or ")))
+            }
+        }
+    }
+
+    // Returns known activities and aliases that are within a small edit distance of name, closest first
+    pub fn suggestions(self: &Self, name: &str) -> Vec<String> {
+        let max_distance = (name.chars().count() / 3).clamp(1, 3);
+        let mut matches: Vec<(usize, String)> = self.activities.iter()
+            .chain(self.aliases.keys())
+            .map(|known| (edit_distance(name, known), known.clone()))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .collect();
+        matches.sort();
+        matches.into_iter().take(3).map(|(_, known)| known).collect()
+    }
+
+    // Adds an alias for a known activity
+    pub fn add_alias(self: &mut Self, alias: &str, activity: &str) -> Result<(), String> {
+        if self.activities.contains(alias) {
+            return Err(format!("Alias error: \"{}\" is already an activity", alias));
+        }
+        if !self.activities.contains(activity) {
+            return Err(format!("Alias error: unknown activity \"{}\"", activity));
+        }
+        self.aliases.insert(alias.to_string(), activity.to_string());
+        Ok(())
+    }
+
+    // Removes an alias
+    pub fn remove_alias(self: &mut Self, alias: &str) -> Result<(), String> {
+        match self.aliases.remove(alias) {
+            Some(_) => Ok(()),
+            None => Err(format!("Alias error: \"{}\" is not an alias", alias)),
+        }
+    }
+
+    // Tags a known activity with a category (replacing any category it was tagged with)
+    pub fn tag(self: &mut Self, activity: &str, category: &str) -> Result<(), String> {
+        if !self.activities.contains(activity) {
+            return Err(format!("Tag error: unknown activity \"{}\"", activity));
+        }
+        if category.is_empty() {
+            return Err(String::from("Tag error: category cannot be empty"));
+        }
+        self.tags.insert(activity.to_string(), category.to_string());
+        Ok(())
+    }
+
+    // Removes the category an activity is tagged with
+    pub fn untag(self: &mut Self, activity: &str) -> Result<(), String> {
+        match self.tags.remove(activity) {
+            Some(_) => Ok(()),
+            None => Err(format!("Tag error: \"{}\" is not tagged", activity)),
+        }
+    }
+
+    // Returns the category of an activity: the one it is tagged with, or else the part of a hierarchical name before
+    // the first CATEGORY_SEPARATOR (None if it has neither)
+    pub fn category(self: &Self, activity: &str) -> Option<String> {
+        match self.tags.get(activity) {
+            Some(category) => Some(category.clone()),
+            None => activity.split_once(CATEGORY_SEPARATOR)
+                .map(|(category, _)| category.to_string())
+                .filter(|category| !category.is_empty()),
+        }
+    }
+
+    // Replaces an activity with another (such as after a rename), pointing its aliases at the new activity
+    // The new activity keeps its own tag, or else takes over the tag of the old activity
+    pub fn replace(self: &mut Self, old: &str, new: &str) {
+        self.activities.remove(old);
+        self.activities.insert(new.to_string());
+        for activity in self.aliases.values_mut() {
+            if activity == old {
+                *activity = new.to_string();
+            }
+        }
+        if let Some(category) = self.tags.remove(old) {
+            self.tags.entry(new.to_string()).or_insert(category);
+        }
+    }
+
+    // Returns a list (as a String) of the known activities with their first and last used dates,
+    // lifetime totals, and aliases
+    pub fn describe(self: &Self, tracker_data: &TrackerData) -> Result<String, String> {
+        if self.activities.is_empty() {
+            return Err(String::from("Activity error: no known activities"));
+        }
+        let usage = tracker_data.activity_usage();
+        let mut activities: Vec<&String> = self.activities.iter().collect();
+        activities.sort();
+        let mut list = String::from("ACTIVITY\tFIRST USED\tLAST USED\tTOTAL TIME\tALIASES\n");
+        for activity in activities {
+            let activity_tab = if activity.len() < 8 {"\t\t"} else {"\t"};
+            let (first, last, total) = match usage.get(activity) {
+                Some((first, last, total)) => (first.to_string(), last.to_string(), total.to_string()),
+                None => (String::from("-"), String::from("-"), String::from("0")),
+            };
+            let first_tab = if first.len() < 8 {"\t\t"} else {"\t"};
+            let last_tab = if last.len() < 8 {"\t\t"} else {"\t"};
+            let total_tab = if total.len() < 8 {"\t\t"} else {"\t"};
+            let mut aliases: Vec<&String> = self.aliases.iter()
+                .filter(|(_, a)| *a == activity)
+                .map(|(alias, _)| alias)
+                .collect();
+            aliases.sort();
+            let aliases: Vec<String> = aliases.into_iter().cloned().collect();
+            let line = format!("{}{}{}{}{}{}{}{}{}", activity, activity_tab, first, first_tab, last, last_tab, total, total_tab, aliases.join(", "));
+            list.push_str(line.trim_end());
+            list.push('\n');
+        }
+        Ok(list.trim_end_matches("\n").to_string())
+    }
+}
+
+// Private Methods
+impl ActivityRegistry {
+    // Fills this ActivityRegistry with the activities and aliases from the JsonValue object
+    fn from_json(self: &mut Self, registry_json: &JsonValue) -> Result<(), String> {
+        if !registry_json.is_object() {
+            return Err(String::from("From JSON error: JSON cannot be interpreted as ActivityRegistry"));
+        }
+        let mut activities: HashSet<String> = HashSet::new();
+        for activity in registry_json["activities"].members() {
+            if let Some(a) = activity.as_str() {
+                activities.insert(a.to_string());
+            }
+        }
+        let mut aliases: HashMap<String, String> = HashMap::new();
+        for (alias, activity) in registry_json["aliases"].entries() {
+            if let Some(a) = activity.as_str() {
+                aliases.insert(alias.to_string(), a.to_string());
+            }
+        }
+        let mut tags: HashMap<String, String> = HashMap::new();
+        for (activity, category) in registry_json["tags"].entries() {
+            if let Some(c) = category.as_str() {
+                tags.insert(activity.to_string(), c.to_string());
+            }
+        }
+        self.activities = activities;
+        self.aliases = aliases;
+        self.tags = tags;
+        Ok(())
+    }
+
+    // Returns a JsonValue object representing this ActivityRegistry
+    fn to_json(self: &Self) -> JsonValue {
+        let mut activities: Vec<&String> = self.activities.iter().collect();
+        activities.sort();
+        let mut aliases: Vec<(&String, &String)> = self.aliases.iter().collect();
+        aliases.sort();
+        let mut aliases_json = JsonValue::new_object();
+        for (alias, activity) in aliases {
+            aliases_json[alias.as_str()] = activity.as_str().into();
+        }
+        let mut tags: Vec<(&String, &String)> = self.tags.iter().collect();
+        tags.sort();
+        let mut tags_json = JsonValue::new_object();
+        for (activity, category) in tags {
+            tags_json[activity.as_str()] = category.as_str().into();
+        }
+        json::object!{
+            activities: activities.into_iter().map(|a| a.as_str()).collect::<Vec<&str>>(),
+            aliases: aliases_json,
+            tags: tags_json
+        }
+    }
+}
+
+// Returns the number of single character insertions, deletions, and substitutions needed to turn a into b
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    let mut prev_row: Vec<usize> = (0..=b_chars.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut curr_row = vec![i + 1];
+        for (j, b_char) in b_chars.iter().enumerate() {
+            let substitution = prev_row[j] + if a_char == *b_char {0} else {1};
+            curr_row.push(substitution.min(prev_row[j + 1] + 1).min(curr_row[j] + 1));
+        }
+        prev_row = curr_row;
+    }
+    prev_row[b_chars.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::activity_registry::{ActivityRegistry, edit_distance};
+    use crate::tracker_data::TrackerData;
+    use crate::date::Date;
+
+    #[test]
+    fn distance() {
+        assert_eq!(edit_distance("guitar", "guitar"), 0);
+        assert_eq!(edit_distance("gitar", "guitar"), 1);
+        assert_eq!(edit_distance("gutiar", "guitar"), 2);
+        assert_eq!(edit_distance("", "gym"), 3);
+    }
+
+    #[test]
+    fn check() {
+        let mut registry = ActivityRegistry::new();
+        assert_eq!(registry.register("guitar"), Ok(()));
+        assert_eq!(registry.register("exercise"), Ok(()));
+        assert_eq!(registry.add_alias("g", "guitar"), Ok(()));
+
+        // Known activities and aliases are resolved
+        assert_eq!(registry.check("guitar", false), Ok(String::from("guitar")));
+        assert_eq!(registry.check("g", false), Ok(String::from("guitar")));
+
+        // Unknown activities are rejected with suggestions, unless they are new
+        assert_eq!(registry.check("gutiar", false),
+                    Err(String::from("Activity error: unknown activity \"gutiar\", did you mean \"guitar\"? (use \"--new\" to create it)")));
+        assert_eq!(registry.check("piano", false),
+                    Err(String::from("Activity error: unknown activity \"piano\" (use \"--new\" to create it)")));
+        assert_eq!(registry.check("piano", true), Ok(String::from("piano")));
+        assert_eq!(registry.is_known("piano"), true);
+        assert_eq!(registry.names(), vec![String::from("exercise"), String::from("g"), String::from("guitar"), String::from("piano")]);
+
+        // Aliases must point at known activities and cannot shadow them
+        assert_eq!(registry.add_alias("p", "drums"),
+                    Err(String::from("Alias error: unknown activity \"drums\"")));
+        assert_eq!(registry.add_alias("piano", "guitar"),
+                    Err(String::from("Alias error: \"piano\" is already an activity")));
+        assert_eq!(registry.remove_alias("g"), Ok(()));
+        assert_eq!(registry.remove_alias("g"),
+                    Err(String::from("Alias error: \"g\" is not an alias")));
+    }
+
+    #[test]
+    fn replace_and_describe() {
+        let mut tracker_data = TrackerData::new();
+        let date1 = Date::new_from_string("2023-2-1").unwrap();
+        let date2 = Date::new_from_string("2023-3-1").unwrap();
+        assert_eq!(tracker_data.add(date1.clone(), String::from("gym"), 30), Ok(()));
+        assert_eq!(tracker_data.add(date2.clone(), String::from("gym"), 45), Ok(()));
+        let mut registry = ActivityRegistry::new();
+        registry.register_from(&tracker_data);
+        assert_eq!(registry.add_alias("g", "gym"), Ok(()));
+
+        // Replacing an activity carries its aliases over
+        assert!(tracker_data.rename_activity("gym", "exercise", None).is_ok());
+        registry.replace("gym", "exercise");
+        assert_eq!(registry.is_known("gym"), false);
+        assert_eq!(registry.resolve("g"), String::from("exercise"));
+        assert_eq!(registry.describe(&tracker_data),
+                    Ok(String::from("ACTIVITY\tFIRST USED\tLAST USED\tTOTAL TIME\tALIASES\nexercise\t2023-2-1\t2023-3-1\t75\t\tg")));
+
+        // Round trip through JSON
+        let mut loaded = ActivityRegistry::new();
+        assert_eq!(loaded.from_json(&registry.to_json()), Ok(()));
+        assert_eq!(loaded.resolve("g"), String::from("exercise"));
+        assert_eq!(loaded.is_known("exercise"), true);
+    }
+
+    #[test]
+    fn categories() {
+        let mut registry = ActivityRegistry::new();
+        for activity in ["guitar", "work/meetings", "work/coding", "/odd"] {
+            assert_eq!(registry.register(activity), Ok(()));
+        }
+        // Hierarchical names have a category unless they are tagged with another one
+        assert_eq!(registry.category("work/meetings"), Some(String::from("work")));
+        assert_eq!(registry.category("guitar"), None);
+        assert_eq!(registry.category("/odd"), None);
+        assert_eq!(registry.tag("guitar", "music"), Ok(()));
+        assert_eq!(registry.tag("work/coding", "craft"), Ok(()));
+        assert_eq!(registry.category("guitar"), Some(String::from("music")));
+        assert_eq!(registry.category("work/coding"), Some(String::from("craft")));
+        assert_eq!(registry.tag("piano", "music"), Err(String::from("Tag error: unknown activity \"piano\"")));
+
+        // Tags follow renamed activities and survive a round trip through JSON
+        registry.replace("guitar", "bass");
+        let mut loaded = ActivityRegistry::new();
+        assert_eq!(loaded.from_json(&registry.to_json()), Ok(()));
+        assert_eq!(loaded.category("bass"), Some(String::from("music")));
+        assert_eq!(loaded.untag("work/coding"), Ok(()));
+        assert_eq!(loaded.category("work/coding"), Some(String::from("work")));
+        assert_eq!(loaded.untag("work/coding"), Err(String::from("Tag error: \"work/coding\" is not tagged")));
+    }
+}
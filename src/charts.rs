@@ -0,0 +1,143 @@
+use crate::date::Date;
+use crate::goals::Period;
+use crate::tracker_data::TrackerData;
+
+// The blocks a sparkline is drawn with, from the smallest value to the largest
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+// The fills used for the activities in a stacked bar (repeating if there are more activities)
+const FILLS: [char; 6] = ['█', '▓', '▒', '░', '#', '='];
+
+// The narrowest a bar is allowed to be drawn, however narrow the terminal is
+const MIN_BAR_WIDTH: usize = 10;
+
+// Returns the total minutes of an activity in every period (such as every week) from start_date to end_date
+// The first and last periods are cut off at start_date and end_date, so only the dates in the range are counted
+pub fn period_totals(tracker_data: &TrackerData, activity: &str, start_date: &Date, end_date: &Date, period: Period)
+                     -> Result<Vec<(Date, Date, u32)>, String> {
+    if end_date < start_date {
+        return Err(format!("Chart error: end date {} is before start date {}", end_date.to_string(), start_date.to_string()));
+    }
+    let mut totals: Vec<(Date, Date, u32)> = Vec::new();
+    let mut date = start_date.clone();
+    while date <= *end_date {
+        let period_end = period.bounds(&date)?.1;
+        let last = if period_end > *end_date {end_date.clone()} else {period_end};
+        totals.push((date.clone(), last.clone(), tracker_data.total_minutes(activity, &date, &last)));
+        date = match last.add_days(1) {
+            Ok(d) => d,
+            Err(_) => break,
+        };
+    }
+    Ok(totals)
+}
+
+// Returns a sparkline (as a String) of the values, with one block per value scaled to the largest value
+pub fn sparkline(values: &[u32]) -> String {
+    let max = values.iter().copied().max().unwrap_or(0);
+    values.iter().map(|value| {
+        if max == 0 {
+            SPARKS[0]
+        } else {
+            SPARKS[(*value as usize * (SPARKS.len() - 1) + max as usize / 2) / max as usize]
+        }
+    }).collect()
+}
+
+// Returns a horizontal bar chart (as a String) of the minutes of the activities in every period from start_date to
+// end_date, scaled to fit within width columns, with the activities stacked in each bar, followed by a legend with a
+// sparkline and total for each activity
+pub fn bar_chart(tracker_data: &TrackerData, activities: &[String], start_date: &Date, end_date: &Date, period: Period,
+                 width: usize) -> Result<String, String> {
+    let mut columns: Vec<Vec<(Date, Date, u32)>> = Vec::new();
+    for activity in activities {
+        columns.push(period_totals(tracker_data, activity, start_date, end_date, period)?);
+    }
+    let mut chart = format!("Minutes by {} from {} to {}:\n\n", period.to_string(), start_date.to_string(), end_date.to_string());
+    if activities.is_empty() {
+        chart.push_str("No activities");
+        return Ok(chart);
+    }
+    // Every row is a period's label, its bar, and its total
+    let periods: Vec<(Date, Date)> = columns[0].iter().map(|(start, end, _)| (start.clone(), end.clone())).collect();
+    let row_totals: Vec<u32> = (0..periods.len()).map(|i| columns.iter().map(|c| c[i].2).sum()).collect();
+    let max_total = row_totals.iter().copied().max().unwrap_or(0);
+    let label_width = periods.iter().map(|(start, _)| start.to_string().len()).max().unwrap_or(0);
+    let bar_width = width.saturating_sub(label_width + max_total.to_string().len() + 2).max(MIN_BAR_WIDTH);
+    for (i, (start, _)) in periods.iter().enumerate() {
+        // Segments end at the rounded running total, so the stacked bar is as long as the rounded row total
+        let mut bar = String::new();
+        let mut running_total: u32 = 0;
+        let mut drawn: usize = 0;
+        for (j, column) in columns.iter().enumerate() {
+            running_total += column[i].2;
+            let end = scale(running_total, max_total, bar_width);
+            bar.extend(std::iter::repeat_n(FILLS[j % FILLS.len()], end - drawn));
+            drawn = end;
+        }
+        chart.push_str(&format!("{:<label_width$} {} {}\n", start.to_string(), bar, row_totals[i]));
+    }
+    // The legend shows which fill is which activity, along with its trend and total
+    chart.push('\n');
+    let name_width = activities.iter().map(|a| a.chars().count()).max().unwrap_or(0);
+    for (j, (activity, column)) in activities.iter().zip(&columns).enumerate() {
+        let values: Vec<u32> = column.iter().map(|(_, _, minutes)| *minutes).collect();
+        chart.push_str(&format!("{} {:<name_width$} {} {}\n", FILLS[j % FILLS.len()], activity, sparkline(&values),
+                                values.iter().sum::<u32>()));
+    }
+    Ok(chart.trim_end_matches("\n").to_string())
+}
+
+// Returns the number of columns a value takes up when max takes up width columns
+fn scale(value: u32, max: u32, width: usize) -> usize {
+    if max == 0 {
+        0
+    } else {
+        (value as usize * width + max as usize / 2) / max as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::charts::{period_totals, sparkline, bar_chart};
+    use crate::goals::Period;
+    use crate::tracker_data::TrackerData;
+    use crate::date::Date;
+
+    fn date(date_str: &str) -> Date {
+        Date::new_from_string(date_str).unwrap()
+    }
+
+    #[test]
+    fn totals_and_sparklines() {
+        let mut tracker_data = TrackerData::new();
+        for (d, minutes) in [("2023-6-1", 30), ("2023-6-4", 60), ("2023-6-5", 45), ("2023-6-12", 15)] {
+            assert_eq!(tracker_data.add(date(d), String::from("guitar"), minutes), Ok(()));
+        }
+        // Weeks are cut off at the start and end of the range
+        assert_eq!(period_totals(&tracker_data, "guitar", &date("2023-6-2"), &date("2023-6-12"), Period::Week),
+                    Ok(vec![(date("2023-6-2"), date("2023-6-4"), 60),
+                            (date("2023-6-5"), date("2023-6-11"), 45),
+                            (date("2023-6-12"), date("2023-6-12"), 15)]));
+        assert!(period_totals(&tracker_data, "guitar", &date("2023-6-2"), &date("2023-6-1"), Period::Day).is_err());
+        assert_eq!(sparkline(&[0, 15, 30, 60]), String::from("▁▃▅█"));
+        assert_eq!(sparkline(&[0, 0]), String::from("▁▁"));
+    }
+
+    #[test]
+    fn stacked_bars() {
+        let mut tracker_data = TrackerData::new();
+        for (d, activity, minutes) in [("2023-6-1", "guitar", 30), ("2023-6-1", "school", 90), ("2023-6-2", "guitar", 60)] {
+            assert_eq!(tracker_data.add(date(d), String::from(activity), minutes), Ok(()));
+        }
+        let activities = vec![String::from("guitar"), String::from("school")];
+        // With 30 columns, the 8 column labels and the 3 column totals leave 17 columns for the longest bar
+        assert_eq!(bar_chart(&tracker_data, &activities, &date("2023-6-1"), &date("2023-6-3"), Period::Day, 30), Ok(String::from(
+            "Minutes by day from 2023-6-1 to 2023-6-3:\n\n\
+             2023-6-1 ████▓▓▓▓▓▓▓▓▓▓▓▓▓ 120\n\
+             2023-6-2 █████████ 60\n\
+             2023-6-3  0\n\n\
+             █ guitar ▅█▁ 90\n\
+             ▓ school █▁▁ 90")));
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::BTreeSet;
+
+use crate::date::Date;
+use crate::tracker_data::TrackerData;
+
+// Returns a report (as a String) comparing the total minutes of every activity in range A with those in range B
+// (such as this week with last week), with the change from B to A in minutes and as a percentage of B
+// Activities with time in only one of the ranges are marked as new (only in A) or gone (only in B)
+pub fn compare(tracker_data: &TrackerData, range_a: &(Date, Date), range_b: &(Date, Date)) -> Result<String, String> {
+    for (start_date, end_date) in [range_a, range_b] {
+        if end_date < start_date {
+            return Err(format!("Compare error: end date {} is before start date {}", end_date.to_string(), start_date.to_string()));
+        }
+    }
+    let (totals_a, _) = tracker_data.activity_totals(&range_a.0, &range_a.1);
+    let (totals_b, _) = tracker_data.activity_totals(&range_b.0, &range_b.1);
+    let activities: BTreeSet<&String> = totals_a.keys().chain(totals_b.keys()).collect();
+    if activities.is_empty() {
+        return Err(String::from("Compare error: no data in either range"));
+    }
+    let mut report = format!("Comparing {} to {} (A) with {} to {} (B):\n\nACTIVITY\tA\tB\tCHANGE\tPERCENT\n",
+                             range_a.0.to_string(), range_a.1.to_string(), range_b.0.to_string(), range_b.1.to_string());
+    for activity in activities {
+        let minutes_a = totals_a.get(activity).copied().unwrap_or(0);
+        let minutes_b = totals_b.get(activity).copied().unwrap_or(0);
+        let activity_tab = if activity.len() < 8 {"\t\t"} else {"\t"};
+        report.push_str(&format!("{}{}{}\t{}\t{}\t{}\n", activity, activity_tab, minutes_a, minutes_b,
+                                 describe_change(minutes_a, minutes_b), describe_percent(minutes_a, minutes_b)));
+    }
+    let total_a: u32 = totals_a.values().sum();
+    let total_b: u32 = totals_b.values().sum();
+    report.push_str(&format!("TOTAL\t\t{}\t{}\t{}\t{}", total_a, total_b,
+                             describe_change(total_a, total_b), describe_percent(total_a, total_b)));
+    Ok(report)
+}
+
+// Returns the change from b to a in minutes, such as "+30", "-15", or "0"
+fn describe_change(a: u32, b: u32) -> String {
+    let change = a as i64 - b as i64;
+    if change == 0 {
+        String::from("0")
+    } else {
+        format!("{:+}", change)
+    }
+}
+
+// Returns the change from b to a as a rounded percentage of b, such as "+25%", or "new" or "gone" if either is 0
+fn describe_percent(a: u32, b: u32) -> String {
+    match (a, b) {
+        (0, 0) => String::from("-"),
+        (_, 0) => String::from("new"),
+        (0, _) => String::from("gone"),
+        _ => {
+            let percent = ((a as f64 - b as f64) * 100.0 / b as f64).round() as i64;
+            if percent == 0 {
+                String::from("0%")
+            } else {
+                format!("{:+}%", percent)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compare::compare;
+    use crate::tracker_data::TrackerData;
+    use crate::date::Date;
+
+    fn range(start: &str, end: &str) -> (Date, Date) {
+        (Date::new_from_string(start).unwrap(), Date::new_from_string(end).unwrap())
+    }
+
+    #[test]
+    fn this_week_with_last_week() {
+        let mut tracker_data = TrackerData::new();
+        for (date, activity, minutes) in [("2023-5-30", "guitar", 90), ("2023-5-31", "school", 300), ("2023-6-1", "work", 60),
+                                          ("2023-6-6", "guitar", 60), ("2023-6-7", "guitar", 60), ("2023-6-6", "school", 300),
+                                          ("2023-6-8", "reading", 45)] {
+            assert_eq!(tracker_data.add(Date::new_from_string(date).unwrap(), String::from(activity), minutes), Ok(()));
+        }
+        assert_eq!(compare(&tracker_data, &range("2023-6-5", "2023-6-11"), &range("2023-5-29", "2023-6-4")), Ok(String::from(
+            "Comparing 2023-6-5 to 2023-6-11 (A) with 2023-5-29 to 2023-6-4 (B):\n\n\
+             ACTIVITY\tA\tB\tCHANGE\tPERCENT\n\
+             guitar\t\t120\t90\t+30\t+33%\n\
+             reading\t\t45\t0\t+45\tnew\n\
+             school\t\t300\t300\t0\t0%\n\
+             work\t\t0\t60\t-60\tgone\n\
+             TOTAL\t\t465\t450\t+15\t+3%")));
+        assert_eq!(compare(&tracker_data, &range("2023-7-1", "2023-7-7"), &range("2023-7-8", "2023-7-14")),
+                    Err(String::from("Compare error: no data in either range")));
+        assert!(compare(&tracker_data, &range("2023-6-5", "2023-6-1"), &range("2023-5-29", "2023-6-4")).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use crate::date::Date;
+use crate::tracker_data::TrackerData;
+
+// The header row written at the top of exported CSV
+const HEADER: &str = "date,activity,minutes";
+
+// Returns the tracker data (only from start_date to end_date, if there is a range) as CSV with
+// one "date,activity,minutes" row per activity on each date
+pub fn to_csv(tracker_data: &TrackerData, range: Option<(Date, Date)>) -> String {
+    let mut csv = format!("{}\n", HEADER);
+    for (date, activity, minutes) in tracker_data.entries() {
+        if let Some((start_date, end_date)) = &range {
+            if &date < start_date || &date > end_date {
+                continue;
+            }
+        }
+        csv.push_str(&format!("{},{},{}\n", date.to_iso_string(), quote_field(&activity), minutes));
+    }
+    csv
+}
+
+// Returns the (date, activity, minutes) rows in CSV contents (a "date,activity,minutes" header row is skipped)
+pub fn from_csv(contents: &str) -> Result<Vec<(Date, String, u16)>, String> {
+    let mut rows: Vec<(Date, String, u16)> = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() || (i == 0 && line.trim().eq_ignore_ascii_case(HEADER)) {
+            continue;
+        }
+        let fields = split_fields(line)
+            .ok_or(format!("CSV error: line {}: unterminated quoted field", i + 1))?;
+        if fields.len() != 3 {
+            return Err(format!("CSV error: line {}: expected 3 fields but found {}", i + 1, fields.len()));
+        }
+        let date = Date::new_from_string(fields[0].trim())
+            .map_err(|_| format!("CSV error: line {}: \"{}\" cannot be interpreted as a date", i + 1, fields[0]))?;
+        let activity = fields[1].trim().to_string();
+        if activity.is_empty() {
+            return Err(format!("CSV error: line {}: activity is empty", i + 1));
+        }
+        let minutes = fields[2].trim().parse::<u16>()
+            .map_err(|_| format!("CSV error: line {}: \"{}\" cannot be interpreted as minutes", i + 1, fields[2]))?;
+        rows.push((date, activity, minutes));
+    }
+    Ok(rows)
+}
+
+// Quotes a field if it contains a comma, quote, or newline
+fn quote_field(field: &str) -> String {
+    if field.contains(',') || field.contains('"') || field.contains('\n') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// Splits a CSV line into its fields, handling quoted fields (returns None if a quote is left open)
+pub(crate) fn split_fields(line: &str) -> Option<Vec<String>> {
+    let mut fields: Vec<String> = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) => {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    in_quotes = false;
+                }
+            }
+            ('"', false) => in_quotes = true,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            (c, _) => field.push(c),
+        }
+    }
+    if in_quotes {
+        return None;
+    }
+    fields.push(field);
+    Some(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::csv::{to_csv, from_csv};
+    use crate::tracker_data::TrackerData;
+    use crate::date::Date;
+
+    #[test]
+    fn export() {
+        let mut tracker_data = TrackerData::new();
+        let date1 = Date::new_from_string("2023-2-1").unwrap();
+        let date2 = Date::new_from_string("2023-3-1").unwrap();
+        assert_eq!(tracker_data.add(date1.clone(), String::from("school"), 180), Ok(()));
+        assert_eq!(tracker_data.add(date1.clone(), String::from("guitar, electric"), 30), Ok(()));
+        assert_eq!(tracker_data.add(date2.clone(), String::from("work"), 120), Ok(()));
+        assert_eq!(to_csv(&tracker_data, None),
+                    String::from("date,activity,minutes\n2023-02-01,\"guitar, electric\",30\n2023-02-01,school,180\n2023-03-01,work,120\n"));
+        assert_eq!(to_csv(&tracker_data, Some((date2.clone(), date2.clone()))),
+                    String::from("date,activity,minutes\n2023-03-01,work,120\n"));
+    }
+
+    #[test]
+    fn import() {
+        // Rows round trip through CSV
+        let rows = from_csv("date,activity,minutes\n2023-02-01,\"guitar, \"\"electric\"\"\",30\n\n2023-3-1, work ,120\n").unwrap();
+        assert_eq!(rows, vec![(Date::new_from_string("2023-2-1").unwrap(), String::from("guitar, \"electric\""), 30),
+                              (Date::new_from_string("2023-3-1").unwrap(), String::from("work"), 120)]);
+
+        // Bad rows are reported with their line numbers
+        assert_eq!(from_csv("2023-02-01,guitar"),
+                    Err(String::from("CSV error: line 1: expected 3 fields but found 2")));
+        assert_eq!(from_csv("date,activity,minutes\n2023-02-30,guitar,30"),
+                    Err(String::from("CSV error: line 2: \"2023-02-30\" cannot be interpreted as a date")));
+        assert_eq!(from_csv("2023-02-01,guitar,half an hour"),
+                    Err(String::from("CSV error: line 1: \"half an hour\" cannot be interpreted as minutes")));
+        assert_eq!(from_csv("2023-02-01,\"guitar,30"),
+                    Err(String::from("CSV error: line 1: unterminated quoted field")));
+    }
+}
@@ -107,11 +107,26 @@ impl Date {
         format!("{}-{}-{}", self.year.to_string(),self.month.to_string(), self.day.to_string())
     }
 
+    // Get a zero-padded ISO 8601 representation of this Date (such as "2023-06-01"), which sorts in date order
+    pub fn to_iso_string(self: &Self) -> String {
+        format!("{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+
     // Get a tuple representation of this Date
     pub fn to_tuple(self: &Self) -> (u16, u16, u16) {
         (self.year, self.month, self.day)
     }
 
+    // Get the day of the week of this Date, counting from Monday (0) to Sunday (6)
+    pub fn weekday(self: &Self) -> u16 {
+        // Sakamoto's method, where January and February count as months of the previous year
+        let offsets: [i32; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+        let year = self.year as i32 - if self.month < 3 {1} else {0};
+        let from_sunday = (year + year.div_euclid(4) - year.div_euclid(100) + year.div_euclid(400)
+                           + offsets[(self.month - 1) as usize] + self.day as i32).rem_euclid(7);
+        ((from_sunday + 6) % 7) as u16
+    }
+
     // Add a certain number of days to this Date and return the result
     pub fn add_days(self: &Self, days: u16) -> Result<Date, String> {
         let mut new_date = self.clone();
@@ -237,6 +252,9 @@ mod tests {
         // Representing as string
         assert_eq!(date1.to_string(), String::from("2000-1-1"));
         assert_eq!(date2.to_string(), String::from("2023-5-27"));
+        // Representing as ISO 8601 string
+        assert_eq!(date1.to_iso_string(), String::from("2000-01-01"));
+        assert_eq!(date2.to_iso_string(), String::from("2023-05-27"));
         // Representing as tuple
         assert_eq!(date1.to_tuple(), (2000, 1, 1));
         assert_eq!(date2.to_tuple(), (2023, 5, 27));
@@ -273,6 +291,15 @@ mod tests {
         assert_eq!(date.set_from_ints(2100, 2, 29), Err(String::from("Set date error: day too large")));
     }
 
+    #[test]
+    fn weekdays() {
+        assert_eq!(Date::new_from_ints(2023, 6, 5).unwrap().weekday(), 0);
+        assert_eq!(Date::new_from_ints(2000, 1, 1).unwrap().weekday(), 5);
+        assert_eq!(Date::new_from_ints(2024, 2, 29).unwrap().weekday(), 3);
+        assert_eq!(Date::new_from_ints(2023, 12, 31).unwrap().weekday(), 6);
+        assert_eq!(Date::new_from_ints(0, 1, 1).unwrap().weekday(), 5);
+    }
+
     #[test]
     fn add_sub_days() {
         let mut date = Date::new();
@@ -0,0 +1,221 @@
+use std::env;
+use std::io::{self, IsTerminal};
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use json::JsonValue;
+
+// The environment variable the passphrase is read from (before asking for it)
+pub const PASSPHRASE_VAR: &str = "TRACKER_PASSPHRASE";
+
+// The salt, nonce, and ciphertext of an encrypted file
+type Envelope = ([u8; 16], Vec<u8>, Vec<u8>);
+
+// Struct Definition
+// A key derived from a passphrase and a random salt with Argon2id, used to encrypt the data file with XChaCha20-Poly1305
+// Every save uses a new random nonce, but keeps the salt (and so the key) of the file
+#[derive(Clone)]
+pub struct Cipher {
+    key: [u8; 32],
+    salt: [u8; 16],
+}
+
+// Public Methods
+impl Cipher {
+    // Creates a new Cipher for a passphrase with a new random salt
+    pub fn new(passphrase: &str) -> Result<Cipher, String> {
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        Cipher::new_from_salt(passphrase, salt)
+    }
+
+    // Returns the contents encrypted into a JSON envelope (which records everything but the passphrase needed to decrypt it)
+    pub fn encrypt(self: &Self, contents: &str) -> Result<String, String> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = XChaCha20Poly1305::new(Key::from_slice(&self.key))
+            .encrypt(&nonce, contents.as_bytes())
+            .map_err(|_| String::from("Encrypt error: cannot encrypt contents"))?;
+        let envelope = json::object!{
+            encryption: "xchacha20poly1305",
+            kdf: "argon2id",
+            salt: to_hex(&self.salt),
+            nonce: to_hex(&nonce),
+            ciphertext: to_hex(&ciphertext)
+        };
+        Ok(envelope.pretty(4))
+    }
+
+    // Returns the contents of an encrypted envelope, which must have been encrypted with the key of this Cipher
+    pub fn decrypt(self: &Self, contents: &str) -> Result<String, String> {
+        let (salt, nonce, ciphertext) = open_envelope(contents)?;
+        if salt != self.salt {
+            return Err(String::from("Decrypt error: contents were encrypted with a different key"));
+        }
+        let plaintext = XChaCha20Poly1305::new(Key::from_slice(&self.key))
+            .decrypt(XNonce::from_slice(&nonce), ciphertext.as_ref())
+            .map_err(|_| String::from("Decrypt error: wrong passphrase, or the file is damaged"))?;
+        String::from_utf8(plaintext).map_err(|_| String::from("Decrypt error: contents cannot be interpreted as an encrypted file"))
+    }
+
+    // Returns whether an encrypted envelope was encrypted with the key of this Cipher (the same passphrase and salt)
+    pub fn encrypted(self: &Self, contents: &str) -> bool {
+        open_envelope(contents).is_ok_and(|(salt, _, _)| salt == self.salt)
+    }
+}
+
+// Returns whether the contents of a file are an encrypted envelope
+pub fn is_encrypted(contents: &str) -> bool {
+    contents.trim_start().starts_with('{') && json::parse(contents).is_ok_and(|parsed| parsed.has_key("encryption"))
+}
+
+// Decrypts an encrypted envelope with the passphrase, returning the contents and the Cipher to encrypt them again with
+pub fn decrypt(contents: &str, passphrase: &str) -> Result<(String, Cipher), String> {
+    let (salt, _, _) = open_envelope(contents)?;
+    let cipher = Cipher::new_from_salt(passphrase, salt)?;
+    Ok((cipher.decrypt(contents)?, cipher))
+}
+
+// Returns the decrypted contents of a file along with its Cipher if it is encrypted, or else the contents themselves
+// A file encrypted with the key of the given cipher (such as the other files of a profile, which are encrypted along
+// with its data file) is decrypted with it, otherwise with the passphrase in the environment variable, or else with
+// the passphrase the user is asked for (such as for a file from someone else)
+pub fn decrypt_if_encrypted(contents: &str, cipher: &Option<Cipher>, filename: &str) -> Result<(String, Option<Cipher>), String> {
+    if !is_encrypted(contents) {
+        return Ok((contents.to_string(), None));
+    }
+    if let Some(c) = cipher.as_ref().filter(|c| c.encrypted(contents)) {
+        return Ok((c.decrypt(contents)?, Some(c.clone())));
+    }
+    if let Ok(p) = env::var(PASSPHRASE_VAR) {
+        match decrypt(contents, &p) {
+            Ok((plaintext, cipher)) => return Ok((plaintext, Some(cipher))),
+            Err(e) if !io::stdin().is_terminal() => return Err(e),
+            Err(_) => {}
+        }
+    }
+    let (plaintext, cipher) = decrypt(contents, &passphrase(filename)?)?;
+    Ok((plaintext, Some(cipher)))
+}
+
+// Returns the contents encrypted with the cipher, or the contents themselves if there is no cipher
+pub fn encrypt_if(cipher: &Option<Cipher>, contents: String) -> Result<String, String> {
+    match cipher {
+        Some(c) => c.encrypt(&contents),
+        None => Ok(contents),
+    }
+}
+
+// Returns the passphrase for an encrypted file by asking for it
+pub fn passphrase(filename: &str) -> Result<String, String> {
+    rpassword::prompt_password(format!("Passphrase for {}: ", filename))
+        .map_err(|_| format!("Passphrase error: cannot read passphrase (set {} instead)", PASSPHRASE_VAR))
+}
+
+// Returns a new passphrase for encrypting a file, from the environment variable or else by asking for it twice
+pub fn new_passphrase() -> Result<String, String> {
+    if let Ok(p) = env::var(PASSPHRASE_VAR) {
+        return check_new_passphrase(p);
+    }
+    let error = format!("Passphrase error: cannot read passphrase (set {} instead)", PASSPHRASE_VAR);
+    let p = rpassword::prompt_password("New passphrase: ").map_err(|_| error.clone())?;
+    let repeated = rpassword::prompt_password("Repeat passphrase: ").map_err(|_| error)?;
+    if p != repeated {
+        return Err(String::from("Passphrase error: passphrases do not match"));
+    }
+    check_new_passphrase(p)
+}
+
+// Private Methods
+impl Cipher {
+    // Creates a new Cipher for a passphrase and salt
+    fn new_from_salt(passphrase: &str, salt: [u8; 16]) -> Result<Cipher, String> {
+        let mut key = [0u8; 32];
+        Argon2::default().hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|_| String::from("Encrypt error: cannot derive key from passphrase"))?;
+        Ok(Cipher {key, salt})
+    }
+}
+
+// Returns the salt, nonce, and ciphertext of an encrypted envelope
+fn open_envelope(contents: &str) -> Result<Envelope, String> {
+    let error = String::from("Decrypt error: contents cannot be interpreted as an encrypted file");
+    let envelope: JsonValue = json::parse(contents).map_err(|_| error.clone())?;
+    if envelope["encryption"].as_str() != Some("xchacha20poly1305") || envelope["kdf"].as_str() != Some("argon2id") {
+        return Err(String::from("Decrypt error: unknown encryption (written by a newer version of tracker?)"));
+    }
+    let field = |name: &str| envelope[name].as_str().and_then(from_hex).ok_or(error.clone());
+    let salt: [u8; 16] = field("salt")?.try_into().map_err(|_| error.clone())?;
+    let nonce = field("nonce")?;
+    if nonce.len() != 24 {
+        return Err(error);
+    }
+    Ok((salt, nonce, field("ciphertext")?))
+}
+
+// Makes sure a new passphrase is not empty
+fn check_new_passphrase(passphrase: String) -> Result<String, String> {
+    if passphrase.is_empty() {
+        Err(String::from("Passphrase error: passphrase is empty"))
+    } else {
+        Ok(passphrase)
+    }
+}
+
+// Returns the bytes as a lowercase hexadecimal string
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Returns the bytes in a hexadecimal string (None if it is not one)
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::encryption::{Cipher, is_encrypted, decrypt, decrypt_if_encrypted};
+
+    #[test]
+    fn encrypt_decrypt() {
+        let contents = "{\"version\": 2, \"data\": {\"2023-02-01\": {\"therapy\": 50}}}";
+        let cipher = Cipher::new("correct horse").unwrap();
+        let encrypted = cipher.encrypt(contents).unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert!(!is_encrypted(contents));
+        assert!(!encrypted.contains("therapy"));
+
+        // Decrypting gives back the contents and a Cipher with the same key, which uses a new nonce every time
+        let (decrypted, same_cipher) = decrypt(&encrypted, "correct horse").unwrap();
+        assert_eq!(decrypted, contents);
+        let encrypted_again = same_cipher.encrypt(contents).unwrap();
+        assert_ne!(encrypted_again, encrypted);
+        assert_eq!(decrypt(&encrypted_again, "correct horse").unwrap().0, contents);
+
+        // The wrong passphrase and damaged files are refused
+        assert_eq!(decrypt(&encrypted, "wrong horse").err(),
+                    Some(String::from("Decrypt error: wrong passphrase, or the file is damaged")));
+        let damaged = encrypted.replace("\"ciphertext\": \"", "\"ciphertext\": \"00");
+        assert!(decrypt(&damaged, "correct horse").is_err());
+    }
+
+    #[test]
+    fn known_cipher() {
+        // A file encrypted with the key of a known cipher is decrypted without reading the passphrase
+        let cipher = Cipher::new("correct horse").unwrap();
+        let encrypted = cipher.encrypt("{}").unwrap();
+        assert!(cipher.encrypted(&encrypted));
+        assert_eq!(decrypt_if_encrypted(&encrypted, &Some(cipher.clone()), "test.json").map(|(c, _)| c), Ok(String::from("{}")));
+        assert_eq!(decrypt_if_encrypted("{}", &Some(cipher.clone()), "test.json").map(|(_, c)| c.is_none()), Ok(true));
+
+        // A file encrypted separately has another salt (even with the same passphrase), so it needs its passphrase read
+        let other = Cipher::new("correct horse").unwrap();
+        let encrypted_other = other.encrypt("{}").unwrap();
+        assert!(!cipher.encrypted(&encrypted_other));
+        assert_eq!(cipher.decrypt(&encrypted_other), Err(String::from("Decrypt error: contents were encrypted with a different key")));
+        assert_eq!(decrypt(&encrypted_other, "correct horse").map(|(c, _)| c), Ok(String::from("{}")));
+    }
+}
@@ -0,0 +1,157 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::prelude::*;
+
+use crate::journal::JournalEntry;
+use crate::storage::{Storage, JsonFileStorage};
+use crate::tracker_data::TrackerData;
+
+// Struct Definitions
+// An append-only log of the changes made to a TrackerData, stored as one JSON object per line
+pub struct EventLog {
+    filename: String,
+}
+
+// Storage in a JSON snapshot file plus an event log of the changes made since the snapshot was saved
+pub struct EventLogStorage {
+    snapshot: JsonFileStorage,
+    event_log: EventLog,
+}
+
+// Public Methods
+impl EventLog {
+    // Creates a new EventLog stored in the file filename
+    pub fn new(filename: &str) -> EventLog {
+        EventLog {
+            filename: filename.to_string(),
+        }
+    }
+
+    // Appends an event to the end of the log
+    pub fn append(self: &Self, entry: &JournalEntry) -> Result<(), String> {
+        let filename = &self.filename;
+        match OpenOptions::new().create(true).append(true).open(filename) {
+            Ok(mut file) => {
+                match writeln!(file, "{}", entry.to_json().dump()) {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err(format!("Event log error: cannot write to \"{filename}\"")),
+                }
+            }
+            Err(_) => Err(format!("Event log error: cannot open \"{filename}\"")),
+        }
+    }
+
+    // Returns every event in the log, oldest first (a missing log has no events)
+    pub fn events(self: &Self) -> Result<Vec<JournalEntry>, String> {
+        let filename = &self.filename;
+        let mut contents = String::new();
+        if let Ok(mut file) = File::open(filename) {
+            match file.read_to_string(&mut contents) {
+                Ok(_) => {}
+                Err(_) => {return Err(format!("Event log error: cannot read \"{filename}\""));}
+            }
+        }
+        let mut events: Vec<JournalEntry> = Vec::new();
+        for (i, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match json::parse(line).ok().and_then(|parsed| JournalEntry::from_json(&parsed).ok()) {
+                Some(entry) => events.push(entry),
+                None => {return Err(format!("Event log error: cannot parse line {} of \"{filename}\"", i + 1));}
+            }
+        }
+        Ok(events)
+    }
+
+    // Rebuilds the tracker data by applying every event in the log on top of it, returning the number of events
+    pub fn replay(self: &Self, tracker_data: &mut TrackerData) -> Result<usize, String> {
+        let events = self.events()?;
+        for entry in &events {
+            tracker_data.apply_changes(&entry.changes, false);
+        }
+        Ok(events.len())
+    }
+
+    // Removes every event from the log (such as after folding them into a snapshot)
+    pub fn clear(self: &Self) -> Result<(), String> {
+        let filename = &self.filename;
+        match fs::remove_file(filename) {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(_) => Err(format!("Event log error: cannot remove \"{filename}\"")),
+        }
+    }
+}
+
+impl EventLogStorage {
+    // Creates a new EventLogStorage using the snapshot file filename and the event log file log_filename
+    pub fn new(filename: &str, log_filename: &str) -> EventLogStorage {
+        EventLogStorage {
+            snapshot: JsonFileStorage::new(filename),
+            event_log: EventLog::new(log_filename),
+        }
+    }
+}
+
+impl Storage for EventLogStorage {
+    // Loads the snapshot and replays the event log on top of it
+    fn load(self: &mut Self) -> Result<TrackerData, String> {
+        let mut tracker_data = self.snapshot.load()?;
+        self.event_log.replay(&mut tracker_data)?;
+        Ok(tracker_data)
+    }
+
+    // Saves a new snapshot and folds the event log into it
+    fn save(self: &mut Self, tracker_data: &TrackerData) -> Result<(), String> {
+        self.snapshot.save(tracker_data)?;
+        self.event_log.clear()
+    }
+
+    // Appends the operation to the event log without touching the snapshot
+    fn apply(self: &mut Self, _tracker_data: &TrackerData, entry: &JournalEntry) -> Result<(), String> {
+        if entry.changes.is_empty() {
+            Ok(())
+        } else {
+            self.event_log.append(entry)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::event_log::EventLog;
+    use crate::journal::JournalEntry;
+    use crate::tracker_data::TrackerData;
+    use crate::date::Date;
+
+    #[test]
+    fn append_replay() {
+        let filename = std::env::temp_dir().join("tracker_event_log_test.jsonl");
+        let event_log = EventLog::new(filename.to_str().unwrap());
+        assert_eq!(event_log.clear(), Ok(()));
+
+        // Append a couple of events
+        let mut tracker_data = TrackerData::new();
+        let date = Date::new_from_string("2023-2-1").unwrap();
+        let before = tracker_data.clone();
+        assert_eq!(tracker_data.add(date.clone(), String::from("guitar"), 30), Ok(()));
+        assert_eq!(event_log.append(&JournalEntry::new("add 30 guitar on 2023-2-1", before.diff(&tracker_data))), Ok(()));
+        let before = tracker_data.clone();
+        assert_eq!(tracker_data.add(date.clone(), String::from("guitar"), 15), Ok(()));
+        assert_eq!(event_log.append(&JournalEntry::new("add 15 guitar on 2023-2-1", before.diff(&tracker_data))), Ok(()));
+
+        // Replaying the log rebuilds the same data
+        let mut replayed = TrackerData::new();
+        assert_eq!(event_log.replay(&mut replayed), Ok(2));
+        assert_eq!(replayed.get(&date, "guitar"), Some(45));
+
+        // A corrupted line is reported
+        fs::write(&filename, "{\"time\": \"oops\"}\n").unwrap();
+        assert!(event_log.replay(&mut replayed).unwrap_err().starts_with("Event log error: cannot parse line 1"));
+
+        // Clearing the log leaves nothing to replay
+        assert_eq!(event_log.clear(), Ok(()));
+        assert_eq!(event_log.replay(&mut replayed), Ok(0));
+    }
+}
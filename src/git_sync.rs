@@ -0,0 +1,201 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::encryption::Cipher;
+use crate::merge::{Conflict, DayMinutes, MergePolicy, merge_three_way, conflict_report};
+use crate::tracker_data::TrackerData;
+
+// Struct Definition
+// A git work tree containing the data file (every git command is run by the git binary in this directory)
+pub struct GitRepo {
+    dir: PathBuf,
+}
+
+// Public Methods
+impl GitRepo {
+    // Opens the git work tree containing the directory dir
+    pub fn open(dir: &Path) -> Result<GitRepo, String> {
+        let repo = GitRepo {dir: dir.to_path_buf()};
+        match repo.git(&["rev-parse", "--is-inside-work-tree"]) {
+            Ok(output) if output.trim() == "true" => Ok(repo),
+            _ => Err(format!("Git error: \"{}\" is not inside a git work tree", dir.display())),
+        }
+    }
+
+    // Commits the files (relative to the directory) with the message, if any of them changed
+    pub fn autocommit(self: &Self, files: &[&str], message: &str) -> Result<bool, String> {
+        let existing: Vec<&str> = files.iter().copied().filter(|f| self.dir.join(f).exists() || self.is_tracked(f)).collect();
+        if existing.is_empty() {
+            return Ok(false);
+        }
+        let mut add_args = vec!["add", "--all", "--"];
+        add_args.extend(&existing);
+        self.git(&add_args)?;
+        let mut diff_args = vec!["diff", "--cached", "--quiet", "--"];
+        diff_args.extend(&existing);
+        if self.succeeds(&diff_args) {
+            return Ok(false);
+        }
+        let mut commit_args = vec!["commit", "--quiet", "-m", message, "--"];
+        commit_args.extend(&existing);
+        self.git(&commit_args)?;
+        Ok(true)
+    }
+
+    // Pulls from the remote, merges the data file at the TrackerData level (resolving activities both sides changed
+    // by the policy), and pushes the result, returning a report of what happened
+    // Local changes to the data file should be committed first
+    // An encrypted data file is read with the cipher (or with its passphrase if it was encrypted with another key)
+    pub fn sync(self: &Self, filename: &str, cipher: &Option<Cipher>, remote: &str, policy: MergePolicy,
+                choose: &mut dyn FnMut(&Conflict) -> Result<DayMinutes, String>) -> Result<String, String> {
+        let branch = self.git(&["rev-parse", "--abbrev-ref", "HEAD"])?.trim().to_string();
+        let remote_ref = format!("{}/{}", remote, branch);
+        self.git(&["fetch", "--quiet", remote])?;
+        // A remote without the branch yet only needs to be pushed to
+        let mut report: String;
+        if !self.succeeds(&["rev-parse", "--verify", "--quiet", &format!("refs/remotes/{}", remote_ref)]) {
+            report = format!("{} does not have branch {} yet", remote, branch);
+        } else if self.succeeds(&["merge-base", "--is-ancestor", &remote_ref, "HEAD"]) {
+            report = format!("Already up to date with {}", remote_ref);
+        } else if self.succeeds(&["merge-base", "--is-ancestor", "HEAD", &remote_ref]) {
+            self.git(&["merge", "--quiet", "--ff-only", &remote_ref])?;
+            report = format!("Fast-forwarded to {}", remote_ref);
+        } else {
+            // Histories started separately (such as on two computers) have no common base, so everything is new to both
+            let base = match self.git(&["merge-base", "HEAD", &remote_ref]) {
+                Ok(base_rev) => self.tracker_data_at(base_rev.trim(), filename, cipher)?,
+                Err(_) => TrackerData::new(),
+            };
+            let mut ours = self.tracker_data_at("HEAD", filename, cipher)?;
+            let theirs = self.tracker_data_at(&remote_ref, filename, cipher)?;
+            let resolved = merge_three_way(&base, &mut ours, &theirs, policy, choose)?;
+            self.commit_merge(filename, &remote_ref, &ours)?;
+            report = format!("Merged {}\n{}", remote_ref, conflict_report(&resolved));
+        }
+        self.git(&["push", "--quiet", remote, &format!("HEAD:{}", branch)])?;
+        report.push_str(&format!("\nPushed {} to {}", branch, remote));
+        Ok(report)
+    }
+}
+
+// Private Methods
+impl GitRepo {
+    // Runs git with the arguments, returning its standard output (or its standard error if it fails)
+    fn git(self: &Self, args: &[&str]) -> Result<String, String> {
+        let output = Command::new("git").args(args).current_dir(&self.dir).output()
+            .map_err(|_| String::from("Git error: cannot run git (is it installed?)"))?;
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        } else {
+            Err(format!("Git error: \"git {}\" failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()))
+        }
+    }
+
+    // Runs git with the arguments, returning whether it succeeded
+    fn succeeds(self: &Self, args: &[&str]) -> bool {
+        self.git(args).is_ok()
+    }
+
+    // Returns whether git already tracks the file (so that deleting it is committed too)
+    fn is_tracked(self: &Self, file: &str) -> bool {
+        self.succeeds(&["ls-files", "--error-unmatch", "--", file])
+    }
+
+    // Returns the tracker data in the data file at a revision (empty if the file did not exist then)
+    fn tracker_data_at(self: &Self, rev: &str, filename: &str, cipher: &Option<Cipher>) -> Result<TrackerData, String> {
+        let mut tracker_data = TrackerData::new();
+        tracker_data.set_cipher(cipher.clone());
+        if let Ok(contents) = self.git(&["show", &format!("{}:./{}", rev, filename)]) {
+            tracker_data.load_from_string(&contents)
+                .map_err(|e| format!("Sync error: cannot read \"{}\" at {}: {}", filename, rev, e))?;
+        }
+        Ok(tracker_data)
+    }
+
+    // Merges the remote branch, replacing the data file with the merged tracker data, and commits the merge
+    // The merge is abandoned if any other file cannot be merged automatically
+    fn commit_merge(self: &Self, filename: &str, remote_ref: &str, merged: &TrackerData) -> Result<(), String> {
+        // The merge is expected to fail on the data file, which is replaced below
+        let _ = self.git(&["merge", "--quiet", "--no-ff", "--no-commit", "--allow-unrelated-histories", remote_ref]);
+        std::fs::write(self.dir.join(filename), merged.save_to_string()?)
+            .map_err(|_| format!("Sync error: cannot write \"{}\"", filename))?;
+        self.git(&["add", "--", filename])?;
+        let unmerged = self.git(&["diff", "--name-only", "--diff-filter=U"])?;
+        if !unmerged.trim().is_empty() {
+            let _ = self.git(&["merge", "--abort"]);
+            return Err(format!("Sync error: cannot merge {} automatically", unmerged.trim().replace('\n', ", ")));
+        }
+        self.git(&["commit", "--quiet", "-m", &format!("sync: merge {}", remote_ref)])?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+    use crate::git_sync::GitRepo;
+    use crate::merge::{Conflict, DayMinutes, MergePolicy};
+    use crate::tracker_data::TrackerData;
+    use crate::date::Date;
+
+    // Runs git in the directory, panicking if it fails
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git").args(args).current_dir(dir).output().unwrap().status;
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    // Clones the remote into a new work tree with a committer set up
+    fn clone(remote: &Path, dir: &Path) {
+        git(dir.parent().unwrap(), &["clone", "--quiet", remote.to_str().unwrap(), dir.to_str().unwrap()]);
+        git(dir, &["config", "user.name", "Test"]);
+        git(dir, &["config", "user.email", "test@example.com"]);
+        git(dir, &["symbolic-ref", "HEAD", "refs/heads/main"]);
+    }
+
+    // Adds minutes to an activity in the data file of a work tree and commits it
+    fn add_and_commit(repo: &GitRepo, dir: &Path, activity: &str, minutes: u16) {
+        let filename = dir.join("tracker_data.json");
+        let mut tracker_data = TrackerData::new();
+        tracker_data.load_from_file(filename.to_str().unwrap()).unwrap();
+        assert_eq!(tracker_data.add(Date::new_from_string("2023-6-1").unwrap(), String::from(activity), minutes), Ok(()));
+        tracker_data.save_to_file(filename.to_str().unwrap()).unwrap();
+        assert_eq!(repo.autocommit(&["tracker_data.json"], &format!("add {} {}", minutes, activity)), Ok(true));
+    }
+
+    #[test]
+    fn autocommit_and_sync() {
+        let root: PathBuf = std::env::temp_dir().join("tracker_git_sync_test");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        let (remote, laptop, desktop) = (root.join("remote.git"), root.join("laptop"), root.join("desktop"));
+        git(&root, &["init", "--quiet", "--bare", remote.to_str().unwrap()]);
+        git(&remote, &["symbolic-ref", "HEAD", "refs/heads/main"]);
+        let mut never_asked = |_: &Conflict| -> Result<DayMinutes, String> { panic!("should not ask") };
+
+        // The laptop pushes first, and the desktop starts from there
+        clone(&remote, &laptop);
+        let laptop_repo = GitRepo::open(&laptop).unwrap();
+        add_and_commit(&laptop_repo, &laptop, "guitar", 30);
+        assert_eq!(laptop_repo.autocommit(&["tracker_data.json"], "nothing changed"), Ok(false));
+        assert!(laptop_repo.sync("tracker_data.json", &None, "origin", MergePolicy::Max, &mut never_asked).is_ok());
+        clone(&remote, &desktop);
+        let desktop_repo = GitRepo::open(&desktop).unwrap();
+
+        // Both add different activities to the same date, which would conflict as text but not as tracker data
+        add_and_commit(&laptop_repo, &laptop, "school", 120);
+        add_and_commit(&desktop_repo, &desktop, "work", 60);
+        assert!(laptop_repo.sync("tracker_data.json", &None, "origin", MergePolicy::Max, &mut never_asked).is_ok());
+        let report = desktop_repo.sync("tracker_data.json", &None, "origin", MergePolicy::Max, &mut never_asked).unwrap();
+        assert!(report.starts_with("Merged origin/main\nNo conflicts"));
+        assert!(laptop_repo.sync("tracker_data.json", &None, "origin", MergePolicy::Max, &mut never_asked).unwrap().starts_with("Fast-forwarded"));
+        let mut tracker_data = TrackerData::new();
+        tracker_data.load_from_file(laptop.join("tracker_data.json").to_str().unwrap()).unwrap();
+        let date = Date::new_from_string("2023-6-1").unwrap();
+        assert_eq!(tracker_data.entries(), vec![(date.clone(), String::from("guitar"), 30),
+                                                (date.clone(), String::from("school"), 120),
+                                                (date.clone(), String::from("work"), 60)]);
+        std::fs::remove_dir_all(&root).unwrap();
+        assert!(GitRepo::open(&std::env::temp_dir()).is_err());
+    }
+}
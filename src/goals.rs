@@ -0,0 +1,381 @@
+use std::fs::File;
+use std::io::prelude::*;
+use json::JsonValue;
+
+use crate::date::Date;
+use crate::encryption::{self, Cipher};
+use crate::tracker_data::TrackerData;
+
+// Enum Definition
+// The length of time a goal is measured over (weeks start on Monday)
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Period {
+    Day,
+    Week,
+    Month,
+}
+
+// Enum Definition
+// Whether a goal is a minimum (such as practicing guitar) or a maximum (such as social media)
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Comparison {
+    AtLeast,
+    AtMost,
+}
+
+// Struct Definition
+// A target number of minutes of an activity in every period
+#[derive(Debug, PartialEq, Clone)]
+pub struct Goal {
+    pub activity: String,
+    pub comparison: Comparison,
+    pub minutes: u32,
+    pub period: Period,
+}
+
+// Struct Definition
+// Like the activities file, the goals file is encrypted again when saved if it was encrypted when loaded
+#[derive(Clone)]
+pub struct Goals {
+    goals: Vec<Goal>,
+    cipher: Option<Cipher>,
+}
+
+// Public Methods
+impl Period {
+    // Creates a new Period from a string argument ("day", "week", or "month")
+    pub fn new_from_string(period_str: &str) -> Result<Period, String> {
+        match period_str {
+            "day" | "daily" => Ok(Period::Day),
+            "week" | "weekly" => Ok(Period::Week),
+            "month" | "monthly" => Ok(Period::Month),
+            p => Err(format!("Goal error: \"{}\" is not one of day, week, month", p)),
+        }
+    }
+
+    // Get a string representation of this Period
+    pub fn to_string(self: &Self) -> String {
+        match self {
+            Period::Day => String::from("day"),
+            Period::Week => String::from("week"),
+            Period::Month => String::from("month"),
+        }
+    }
+
+    // Returns the first and last dates of the period containing date
+    pub fn bounds(self: &Self, date: &Date) -> Result<(Date, Date), String> {
+        match self {
+            Period::Day => Ok((date.clone(), date.clone())),
+            Period::Week => {
+                let start = date.sub_days(date.weekday())?;
+                let end = start.add_days(6)?;
+                Ok((start, end))
+            }
+            Period::Month => {
+                let (year, month, _) = date.to_tuple();
+                let start = Date::new_from_ints(year, month, 1)?;
+                let mut end = start.add_days(27)?;
+                while let Ok(next) = end.add_days(1) {
+                    if next.to_tuple().1 != month {
+                        break;
+                    }
+                    end = next;
+                }
+                Ok((start, end))
+            }
+        }
+    }
+}
+
+impl Comparison {
+    // Creates a new Comparison from a string argument ("at-least" or "at-most")
+    pub fn new_from_string(comparison_str: &str) -> Result<Comparison, String> {
+        match comparison_str {
+            "at-least" | ">=" => Ok(Comparison::AtLeast),
+            "at-most" | "<=" => Ok(Comparison::AtMost),
+            c => Err(format!("Goal error: \"{}\" is not one of at-least, at-most", c)),
+        }
+    }
+
+    // Get a string representation of this Comparison
+    pub fn to_string(self: &Self) -> String {
+        match self {
+            Comparison::AtLeast => String::from("at-least"),
+            Comparison::AtMost => String::from("at-most"),
+        }
+    }
+}
+
+impl Goal {
+    // Returns a short description (as a String) of this Goal, such as ">= 30/day"
+    pub fn describe(self: &Self) -> String {
+        let symbol = match self.comparison {
+            Comparison::AtLeast => ">=",
+            Comparison::AtMost => "<=",
+        };
+        format!("{} {}/{}", symbol, self.minutes, self.period.to_string())
+    }
+
+    // Returns whether a total number of minutes in a period meets this Goal
+    pub fn is_met(self: &Self, total: u32) -> bool {
+        match self.comparison {
+            Comparison::AtLeast => total >= self.minutes,
+            Comparison::AtMost => total <= self.minutes,
+        }
+    }
+}
+
+impl Goals {
+    // Creates a new Goals without any goals
+    pub fn new() -> Goals {
+        Goals {
+            goals: Vec::new(),
+            cipher: None,
+        }
+    }
+
+    // Loads JSON formatted goals from file filename into this Goals
+    pub fn load_from_file(self: &mut Self, filename: &str) -> Result<(), String> {
+        // Open the file and get the contents
+        let mut contents = String::new();
+        if let Ok(mut file) = File::open(filename) {
+            match file.read_to_string(&mut contents) {
+                Ok(_) => {}
+                Err(_) => {return Err(format!("Load from file error: cannot read \"{filename}\""));}
+            }
+        }
+        // Decrypt the contents if the file is encrypted (with the current cipher if it has the same key, and keeping the
+        // cipher of the file), then parse them and use them to populate this Goals
+        // A missing or plaintext file keeps the current cipher, so the file of an encrypted profile is encrypted when saved
+        let (contents, cipher) = encryption::decrypt_if_encrypted(&contents, &self.cipher, filename)?;
+        if cipher.is_some() {
+            self.cipher = cipher;
+        }
+        if !contents.is_empty() {
+            match json::parse(&contents) {
+                Ok(parsed) => self.from_json(&parsed)
+                    .map_err(|e| format!("Load from file error: {} in \"{}\"", e, filename))?,
+                Err(_) => {
+                    return Err(format!("Load from file error: cannot parse contents of \"{filename}\""));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Saves JSON formatted goals from this Goals into the file filename
+    pub fn save_to_file(self: &Self, filename: &str) -> Result<(), String> {
+        match File::create(filename) {
+            Ok(mut file) => {
+                match file.write_all(encryption::encrypt_if(&self.cipher, self.to_json().pretty(4))?.as_bytes()) {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err(format!("Save to file error: cannot write to \"{filename}\"")),
+                }
+            }
+            Err(_) => Err(format!("Save to file error: cannot open \"{filename}\"")),
+        }
+    }
+
+    // Changes how this Goals is encrypted when saved (None saves it unencrypted)
+    pub fn set_cipher(self: &mut Self, cipher: Option<Cipher>) {
+        self.cipher = cipher;
+    }
+
+    // Returns every goal (sorted by activity and period)
+    pub fn list(self: &Self) -> &[Goal] {
+        &self.goals
+    }
+
+    // Adds a goal, replacing any goal for the same activity and period
+    pub fn set(self: &mut Self, goal: Goal) {
+        self.goals.retain(|g| g.activity != goal.activity || g.period != goal.period);
+        self.goals.push(goal);
+        self.goals.sort_by(|a, b| (&a.activity, a.period).cmp(&(&b.activity, b.period)));
+    }
+
+    // Removes the goals for an activity (only the one for a period, if it is given)
+    pub fn remove(self: &mut Self, activity: &str, period: Option<Period>) -> Result<(), String> {
+        let count = self.goals.len();
+        self.goals.retain(|g| g.activity != activity || period.is_some_and(|p| p != g.period));
+        if self.goals.len() == count {
+            return Err(format!("Goal error: no goal for \"{}\"", activity));
+        }
+        Ok(())
+    }
+
+    // Returns a report (as a String) of the progress towards every goal in the period containing today,
+    // followed by whether each goal was hit or missed in the given number of periods before that
+    pub fn report(self: &Self, tracker_data: &TrackerData, today: &Date, history: u16) -> Result<String, String> {
+        if self.goals.is_empty() {
+            return Ok(String::from("No goals (use \"goal set\" to add one)"));
+        }
+        let mut report = format!("Progress on {}:\n\nACTIVITY\tGOAL\t\tTOTAL\tSTATUS\t\tPERIOD\n", today.to_string());
+        for goal in &self.goals {
+            let (start_date, end_date) = goal.period.bounds(today)?;
+            let total = tracker_data.total_minutes(&goal.activity, &start_date, &end_date);
+            // A minimum can still be reached later in the period, but a maximum that is passed stays passed
+            let status = match (goal.comparison, goal.is_met(total)) {
+                (Comparison::AtLeast, true) => "met",
+                (Comparison::AtLeast, false) => "in progress",
+                (Comparison::AtMost, true) => "on track",
+                (Comparison::AtMost, false) => "over",
+            };
+            let status_tab = if status.len() < 8 {"\t\t"} else {"\t"};
+            report.push_str(&format!("{}{}{}\t{}\t{}{}{}\n", goal.activity, activity_tab(&goal.activity), goal.describe(),
+                                     total, status, status_tab, describe_period(&start_date, &end_date)));
+        }
+        if history > 0 {
+            report.push_str(&format!("\nHistory of the last {} period(s):\n\nACTIVITY\tGOAL\t\tTOTAL\tRESULT\tPERIOD\n", history));
+            for goal in &self.goals {
+                // Step back from the current period, then list the periods oldest first
+                let mut periods: Vec<(Date, Date)> = Vec::new();
+                let mut date = goal.period.bounds(today)?.0;
+                for _ in 0..history {
+                    date = match date.sub_days(1) {
+                        Ok(d) => d,
+                        Err(_) => break,
+                    };
+                    let (start_date, end_date) = goal.period.bounds(&date)?;
+                    date = start_date.clone();
+                    periods.insert(0, (start_date, end_date));
+                }
+                for (start_date, end_date) in periods {
+                    let total = tracker_data.total_minutes(&goal.activity, &start_date, &end_date);
+                    let result = if goal.is_met(total) {"hit"} else {"miss"};
+                    report.push_str(&format!("{}{}{}\t{}\t{}\t{}\n", goal.activity, activity_tab(&goal.activity), goal.describe(),
+                                             total, result, describe_period(&start_date, &end_date)));
+                }
+            }
+        }
+        Ok(report.trim_end_matches("\n").to_string())
+    }
+}
+
+// Private Methods
+impl Goals {
+    // Populates this Goals from a JSON array of goals
+    fn from_json(self: &mut Self, goals_json: &JsonValue) -> Result<(), String> {
+        for goal_json in goals_json.members() {
+            let activity = goal_json["activity"].as_str().ok_or("missing activity")?;
+            let comparison = Comparison::new_from_string(goal_json["comparison"].as_str().unwrap_or(""))?;
+            let minutes = goal_json["minutes"].as_u32().ok_or("missing minutes")?;
+            let period = Period::new_from_string(goal_json["period"].as_str().unwrap_or(""))?;
+            self.set(Goal {activity: activity.to_string(), comparison, minutes, period});
+        }
+        Ok(())
+    }
+
+    // Returns this Goals as a JSON array of goals
+    fn to_json(self: &Self) -> JsonValue {
+        let mut goals_json = JsonValue::new_array();
+        for goal in &self.goals {
+            goals_json.push(json::object!{
+                activity: goal.activity.as_str(),
+                comparison: goal.comparison.to_string(),
+                minutes: goal.minutes,
+                period: goal.period.to_string()
+            }).unwrap();
+        }
+        goals_json
+    }
+}
+
+// Returns the separator after an activity in a report table
+fn activity_tab(activity: &str) -> &'static str {
+    if activity.len() < 8 {"\t\t"} else {"\t"}
+}
+
+// Returns a description (as a String) of a period, such as "2023-6-5" or "2023-6-5 to 2023-6-11"
+fn describe_period(start_date: &Date, end_date: &Date) -> String {
+    if start_date == end_date {
+        start_date.to_string()
+    } else {
+        format!("{} to {}", start_date.to_string(), end_date.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::goals::{Goals, Goal, Period, Comparison};
+    use crate::encryption::Cipher;
+    use crate::tracker_data::TrackerData;
+    use crate::date::Date;
+
+    #[test]
+    fn periods() {
+        let date = Date::new_from_string("2023-6-7").unwrap();
+        assert_eq!(Period::Day.bounds(&date), Ok((date.clone(), date.clone())));
+        assert_eq!(Period::Week.bounds(&date), Ok((Date::new_from_string("2023-6-5").unwrap(), Date::new_from_string("2023-6-11").unwrap())));
+        assert_eq!(Period::Month.bounds(&date), Ok((Date::new_from_string("2023-6-1").unwrap(), Date::new_from_string("2023-6-30").unwrap())));
+        let leap_day = Date::new_from_string("2024-2-29").unwrap();
+        assert_eq!(Period::Month.bounds(&leap_day), Ok((Date::new_from_string("2024-2-1").unwrap(), leap_day.clone())));
+        assert_eq!(Period::new_from_string("year"), Err(String::from("Goal error: \"year\" is not one of day, week, month")));
+    }
+
+    #[test]
+    fn progress_and_history() {
+        let mut tracker_data = TrackerData::new();
+        for (date, activity, minutes) in [("2023-6-5", "guitar", 35), ("2023-6-6", "guitar", 20), ("2023-6-7", "guitar", 10),
+                                          ("2023-6-6", "social", 45), ("2023-6-7", "social", 75)] {
+            assert_eq!(tracker_data.add(Date::new_from_string(date).unwrap(), String::from(activity), minutes), Ok(()));
+        }
+        let mut goals = Goals::new();
+        goals.set(Goal {activity: String::from("social"), comparison: Comparison::AtMost, minutes: 60, period: Period::Day});
+        goals.set(Goal {activity: String::from("guitar"), comparison: Comparison::AtLeast, minutes: 30, period: Period::Day});
+        goals.set(Goal {activity: String::from("guitar"), comparison: Comparison::AtLeast, minutes: 60, period: Period::Week});
+        let today = Date::new_from_string("2023-6-7").unwrap();
+        assert_eq!(goals.report(&tracker_data, &today, 2), Ok(String::from(
+            "Progress on 2023-6-7:\n\n\
+             ACTIVITY\tGOAL\t\tTOTAL\tSTATUS\t\tPERIOD\n\
+             guitar\t\t>= 30/day\t10\tin progress\t2023-6-7\n\
+             guitar\t\t>= 60/week\t65\tmet\t\t2023-6-5 to 2023-6-11\n\
+             social\t\t<= 60/day\t75\tover\t\t2023-6-7\n\
+             \nHistory of the last 2 period(s):\n\n\
+             ACTIVITY\tGOAL\t\tTOTAL\tRESULT\tPERIOD\n\
+             guitar\t\t>= 30/day\t35\thit\t2023-6-5\n\
+             guitar\t\t>= 30/day\t20\tmiss\t2023-6-6\n\
+             guitar\t\t>= 60/week\t0\tmiss\t2023-5-22 to 2023-5-28\n\
+             guitar\t\t>= 60/week\t0\tmiss\t2023-5-29 to 2023-6-4\n\
+             social\t\t<= 60/day\t0\thit\t2023-6-5\n\
+             social\t\t<= 60/day\t45\thit\t2023-6-6")));
+
+        // Setting a goal for the same activity and period replaces it, and removing without a period removes them all
+        goals.set(Goal {activity: String::from("social"), comparison: Comparison::AtMost, minutes: 90, period: Period::Day});
+        assert!(goals.report(&tracker_data, &today, 0).unwrap().ends_with("social\t\t<= 90/day\t75\ton track\t2023-6-7"));
+        assert_eq!(goals.remove("guitar", Some(Period::Month)), Err(String::from("Goal error: no goal for \"guitar\"")));
+        assert_eq!(goals.remove("guitar", Some(Period::Week)), Ok(()));
+        assert_eq!(goals.remove("guitar", None), Ok(()));
+        assert_eq!(goals.remove("guitar", None), Err(String::from("Goal error: no goal for \"guitar\"")));
+    }
+
+    #[test]
+    fn file() {
+        let filename = std::env::temp_dir().join("tracker_goals_test.json");
+        let filename = filename.to_str().unwrap();
+        let mut goals = Goals::new();
+        goals.set(Goal {activity: String::from("exercise"), comparison: Comparison::AtLeast, minutes: 180, period: Period::Week});
+        goals.save_to_file(filename).unwrap();
+        let mut loaded = Goals::new();
+        loaded.load_from_file(filename).unwrap();
+        assert_eq!(loaded.goals, goals.goals);
+        std::fs::write(filename, "[{\"activity\": \"exercise\", \"comparison\": \"more\", \"minutes\": 180, \"period\": \"week\"}]").unwrap();
+        assert!(Goals::new().load_from_file(filename).unwrap_err().contains("\"more\" is not one of at-least, at-most"));
+        std::fs::remove_file(filename).unwrap();
+    }
+
+    #[test]
+    fn encrypted_profile() {
+        // A goals file that does not exist yet in an encrypted profile is encrypted when it is first saved
+        let filename = std::env::temp_dir().join("tracker_goals_encrypted_test.json");
+        let filename = filename.to_str().unwrap();
+        let _ = std::fs::remove_file(filename);
+        let cipher = Cipher::new("correct horse").unwrap();
+        let mut goals = Goals::new();
+        goals.set_cipher(Some(cipher.clone()));
+        goals.load_from_file(filename).unwrap();
+        goals.set(Goal {activity: String::from("exercise"), comparison: Comparison::AtLeast, minutes: 180, period: Period::Week});
+        goals.save_to_file(filename).unwrap();
+        assert!(cipher.encrypted(&std::fs::read_to_string(filename).unwrap()));
+        std::fs::remove_file(filename).unwrap();
+    }
+}
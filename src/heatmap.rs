@@ -0,0 +1,182 @@
+use crate::date::Date;
+use crate::tracker_data::TrackerData;
+
+// The short names of the months and weekdays used as labels
+pub const MONTH_NAMES: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+pub const WEEKDAY_NAMES: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+// The number of shades for days with time (days without time get their own, lightest shade)
+pub const LEVELS: usize = 4;
+
+// The cells drawn for each shade without color, from no time to the most time
+const BLOCKS: [char; LEVELS + 1] = ['·', '░', '▒', '▓', '█'];
+
+// The ANSI 256-color palette colors drawn for each shade with color, from no time to the most time
+const COLORS: [u8; LEVELS + 1] = [237, 22, 28, 34, 40];
+
+// Struct Definition
+// The minutes of an activity on every day of a year, laid out like a calendar in weeks (starting on Monday) of days
+// Days in the first and last weeks that are outside the year are None
+pub struct Heatmap {
+    pub activity: String,
+    pub year: u16,
+    pub weeks: Vec<[Option<(Date, u32)>; 7]>,
+}
+
+// Public Methods
+impl Heatmap {
+    // Creates a new Heatmap of the minutes of an activity on every day of a year
+    pub fn new(tracker_data: &TrackerData, activity: &str, year: u16) -> Result<Heatmap, String> {
+        let first = Date::new_from_ints(year, 1, 1)?;
+        let last = Date::new_from_ints(year, 12, 31)?;
+        let mut weeks: Vec<[Option<(Date, u32)>; 7]> = Vec::new();
+        let mut date = first.clone();
+        loop {
+            if weeks.is_empty() || date.weekday() == 0 {
+                weeks.push(std::array::from_fn(|_| None));
+            }
+            let minutes = tracker_data.get(&date, activity).unwrap_or(0) as u32;
+            weeks.last_mut().unwrap()[date.weekday() as usize] = Some((date.clone(), minutes));
+            if date == last {
+                break;
+            }
+            date = date.add_days(1)?;
+        }
+        Ok(Heatmap {activity: activity.to_string(), year, weeks})
+    }
+
+    // Returns the most minutes on any day
+    pub fn max(self: &Self) -> u32 {
+        self.days().map(|(_, minutes)| *minutes).max().unwrap_or(0)
+    }
+
+    // Returns the total minutes in the year
+    pub fn total(self: &Self) -> u32 {
+        self.days().map(|(_, minutes)| *minutes).sum()
+    }
+
+    // Returns the shade of a day with some minutes, from 0 (no time) to LEVELS (close to the most minutes on any day)
+    pub fn level(self: &Self, minutes: u32) -> usize {
+        let max = self.max();
+        if minutes == 0 || max == 0 {
+            0
+        } else {
+            (minutes as usize * LEVELS).div_ceil(max as usize).min(LEVELS)
+        }
+    }
+
+    // Returns the range of minutes shaded at each level from 1 to LEVELS (levels without any possible minutes are left out)
+    pub fn level_ranges(self: &Self) -> Vec<(usize, u32, u32)> {
+        let max = self.max() as usize;
+        (1..=LEVELS)
+            .map(|level| (level, ((level - 1) * max / LEVELS + 1) as u32, (level * max / LEVELS) as u32))
+            .filter(|(_, low, high)| low <= high)
+            .collect()
+    }
+
+    // Returns the week (column) in which each month starts, along with the month
+    pub fn month_starts(self: &Self) -> Vec<(usize, u16)> {
+        let mut starts: Vec<(usize, u16)> = Vec::new();
+        for (i, week) in self.weeks.iter().enumerate() {
+            for (date, _) in week.iter().flatten() {
+                let (_, month, day) = date.to_tuple();
+                if day == 1 {
+                    starts.push((i, month));
+                }
+            }
+        }
+        starts
+    }
+
+    // Returns the heatmap drawn (as a String) for a terminal, as a grid of weekdays (rows) by weeks (columns) with month
+    // labels and a legend, using ANSI colors if color is set, or else Unicode shade blocks
+    pub fn render(self: &Self, color: bool) -> String {
+        let mut drawing = format!("{} in {}: {} minutes, at most {} on one day\n\n", self.activity, self.year, self.total(), self.max());
+        // Each month label starts above the week the month starts in, unless the last label is still in the way
+        let mut labels = String::from("    ");
+        for (week, month) in self.month_starts() {
+            if labels.chars().count() <= 4 + week {
+                labels.push_str(&" ".repeat(4 + week - labels.chars().count()));
+                labels.push_str(MONTH_NAMES[month as usize - 1]);
+            }
+        }
+        drawing.push_str(labels.trim_end());
+        drawing.push('\n');
+        for (weekday, name) in WEEKDAY_NAMES.iter().enumerate() {
+            drawing.push_str(&format!("{} ", name));
+            for week in &self.weeks {
+                match &week[weekday] {
+                    Some((_, minutes)) => drawing.push_str(&self.cell(self.level(*minutes), color)),
+                    None => drawing.push(' '),
+                }
+            }
+            drawing = drawing.trim_end().to_string();
+            drawing.push('\n');
+        }
+        // The legend shows the minutes each shade stands for
+        drawing.push_str(&format!("\n{} 0", self.cell(0, color)));
+        for (level, low, high) in self.level_ranges() {
+            drawing.push_str(&format!("  {} {}-{}", self.cell(level, color), low, high));
+        }
+        drawing.push_str(" minutes");
+        drawing
+    }
+}
+
+// Private Methods
+impl Heatmap {
+    // Returns every day in the year and its minutes
+    fn days(self: &Self) -> impl Iterator<Item = &(Date, u32)> {
+        self.weeks.iter().flat_map(|week| week.iter().flatten())
+    }
+
+    // Returns a cell drawn in the shade of a level
+    fn cell(self: &Self, level: usize, color: bool) -> String {
+        if color {
+            format!("\x1b[38;5;{}m■\x1b[0m", COLORS[level])
+        } else {
+            BLOCKS[level].to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heatmap::Heatmap;
+    use crate::tracker_data::TrackerData;
+    use crate::date::Date;
+
+    #[test]
+    fn model() {
+        let mut tracker_data = TrackerData::new();
+        for (date, minutes) in [("2023-1-1", 10), ("2023-1-2", 120), ("2023-6-15", 45), ("2023-12-31", 90)] {
+            assert_eq!(tracker_data.add(Date::new_from_string(date).unwrap(), String::from("guitar"), minutes), Ok(()));
+        }
+        let heatmap = Heatmap::new(&tracker_data, "guitar", 2023).unwrap();
+        // 2023 starts on a Sunday and ends on a Sunday, so it has one day in its first week and 53 weeks
+        assert_eq!(heatmap.weeks.len(), 53);
+        assert_eq!(heatmap.weeks[0][0], None);
+        assert_eq!(heatmap.weeks[0][6], Some((Date::new_from_string("2023-1-1").unwrap(), 10)));
+        assert_eq!(heatmap.weeks[1][0], Some((Date::new_from_string("2023-1-2").unwrap(), 120)));
+        assert_eq!(heatmap.weeks[52][6], Some((Date::new_from_string("2023-12-31").unwrap(), 90)));
+        assert_eq!((heatmap.total(), heatmap.max()), (265, 120));
+        assert_eq!((heatmap.level(0), heatmap.level(10), heatmap.level(45), heatmap.level(90), heatmap.level(120)), (0, 1, 2, 3, 4));
+        assert_eq!(heatmap.level_ranges(), vec![(1, 1, 30), (2, 31, 60), (3, 61, 90), (4, 91, 120)]);
+        assert_eq!(heatmap.month_starts()[..3], [(0, 1), (5, 2), (9, 3)]);
+    }
+
+    #[test]
+    fn render() {
+        let mut tracker_data = TrackerData::new();
+        assert_eq!(tracker_data.add(Date::new_from_string("2023-1-2").unwrap(), String::from("guitar"), 2), Ok(()));
+        let drawing = Heatmap::new(&tracker_data, "guitar", 2023).unwrap().render(false);
+        let lines: Vec<&str> = drawing.lines().collect();
+        assert_eq!(lines[0], "guitar in 2023: 2 minutes, at most 2 on one day");
+        assert!(lines[2].starts_with("    Jan  Feb Mar Apr"));
+        assert!(lines[3].starts_with("Mon  █····"));
+        assert!(lines[9].starts_with("Sun ··"));
+        assert_eq!(lines[3].chars().count(), 4 + 53);
+        // With only 2 minutes at most, only the levels that can be reached are in the legend
+        assert_eq!(lines[11], "· 0  ▒ 1-1  █ 2-2 minutes");
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::charts::period_totals;
+use crate::date::Date;
+use crate::goals::Period;
+use crate::heatmap::{Heatmap, LEVELS, MONTH_NAMES, WEEKDAY_NAMES};
+use crate::tracker_data::TrackerData;
+
+// The colors of the activities in the stacked bars (repeating if there are more activities)
+const BAR_COLORS: [&str; 8] = ["#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#9c755f"];
+
+// The colors of each shade of a heatmap, from no time to the most time
+const HEATMAP_COLORS: [&str; LEVELS + 1] = ["#ebedf0", "#9be9a8", "#40c463", "#30a14e", "#216e39"];
+
+// The sizes (in pixels) of the parts of the bar chart: the width of the week labels, the width of the longest bar,
+// the height of each bar, and the space between bars
+const LABEL_WIDTH: usize = 90;
+const BAR_AREA_WIDTH: usize = 520;
+const BAR_HEIGHT: usize = 18;
+const BAR_GAP: usize = 6;
+
+// The sizes (in pixels) of the parts of a heatmap: the width of the weekday labels, the height of the month labels,
+// the size of each day's cell, and the space between cells
+const WEEKDAY_LABEL_WIDTH: usize = 32;
+const MONTH_LABEL_HEIGHT: usize = 16;
+const CELL_SIZE: usize = 11;
+const CELL_GAP: usize = 2;
+
+// The style sheet included in every report
+const STYLE: &str = "body { font-family: sans-serif; color: #24292f; margin: 2em auto; max-width: 60em; padding: 0 1em; }
+h1, h2, h3 { font-weight: 600; }
+table { border-collapse: collapse; margin-bottom: 1.5em; }
+th, td { padding: 0.3em 1em; border-bottom: 1px solid #d0d7de; text-align: right; }
+th:first-child, td:first-child { text-align: left; }
+tr.total td { font-weight: 600; }
+svg { display: block; margin-bottom: 1em; font-size: 10px; }
+.legend { list-style: none; padding: 0; }
+.legend li { display: inline-block; margin-right: 1.5em; }
+.swatch { display: inline-block; width: 0.8em; height: 0.8em; margin-right: 0.4em; }";
+
+// Returns a report (as a String) of the activities from start_date to end_date as a single HTML page, which needs
+// nothing but a browser to view: tables of the totals of every activity (and of their categories, from categories,
+// which maps activities to their categories), a stacked bar chart of the minutes in every week, and a heatmap of
+// the days of the range in each of its years for each activity (leaving out years without any time)
+pub fn html_report(tracker_data: &TrackerData, start_date: &Date, end_date: &Date, categories: &HashMap<String, String>)
+                   -> Result<String, String> {
+    if end_date < start_date {
+        return Err(format!("Report error: end date {} is before start date {}", end_date.to_string(), start_date.to_string()));
+    }
+    let (totals, num_days) = tracker_data.activity_totals(start_date, end_date);
+    if totals.is_empty() {
+        return Err(format!("Report error: no data for {} to {}", start_date.to_string(), end_date.to_string()));
+    }
+    let title = format!("Activity report from {} to {}", start_date.to_string(), end_date.to_string());
+    let mut html = format!("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+                            <style>\n{}\n</style>\n</head>\n<body>\n<h1>{}</h1>\n",
+                           title, STYLE, title);
+
+    // The summary tables show the same totals, averages, and shares as the sum function
+    let total: u32 = totals.values().sum();
+    html.push_str(&format!("<h2>Summary</h2>\n<p>{} minutes on {} day(s) with time.</p>\n", total, num_days));
+    html.push_str(&summary_table("Activity", &totals, total, num_days));
+    html.push_str(&format!("<tr class=\"total\"><td>Total</td><td>{}</td><td>{}</td><td>100.0%</td></tr>\n</table>\n",
+                           total, total / num_days));
+    let mut category_totals: BTreeMap<String, u32> = BTreeMap::new();
+    for (activity, minutes) in &totals {
+        if let Some(category) = categories.get(activity) {
+            *category_totals.entry(category.clone()).or_insert(0) += minutes;
+        }
+    }
+    if !category_totals.is_empty() {
+        html.push_str(&summary_table("Category", &category_totals, total, num_days));
+        html.push_str("</table>\n");
+    }
+
+    let activities: Vec<String> = totals.into_keys().collect();
+    html.push_str("<h2>Minutes by week</h2>\n");
+    html.push_str(&week_chart(tracker_data, &activities, start_date, end_date)?);
+
+    // The heatmaps only count the days of the range, even though they are laid out as whole years
+    html.push_str("<h2>Heatmaps</h2>\n");
+    let range_data = tracker_data.range(start_date, end_date);
+    for activity in &activities {
+        for year in start_date.to_tuple().0..=end_date.to_tuple().0 {
+            let heatmap = Heatmap::new(&range_data, activity, year)?;
+            if heatmap.total() > 0 {
+                html.push_str(&heatmap_svg(&heatmap, start_date, end_date));
+            }
+        }
+    }
+    html.push_str("</body>\n</html>\n");
+    Ok(html)
+}
+
+// Returns the start of a table (in HTML, left open for more rows) of the total, average (over num_days), and share of
+// total for every name (an activity or a category)
+fn summary_table(heading: &str, totals: &BTreeMap<String, u32>, total: u32, num_days: u32) -> String {
+    let mut table = format!("<table>\n<tr><th>{}</th><th>Total time</th><th>Avg time</th><th>Share</th></tr>\n", heading);
+    for (name, minutes) in totals {
+        table.push_str(&format!("<tr><td>{}</td><td>{}</td><td>{}</td><td>{:.1}%</td></tr>\n",
+                                escape(name), minutes, minutes / num_days, *minutes as f64 * 100.0 / total as f64));
+    }
+    table
+}
+
+// Returns a horizontal bar chart (as an SVG element) of the minutes of the activities in every week from start_date to
+// end_date, with the activities stacked in each bar, followed by a legend
+fn week_chart(tracker_data: &TrackerData, activities: &[String], start_date: &Date, end_date: &Date) -> Result<String, String> {
+    let mut columns: Vec<Vec<(Date, Date, u32)>> = Vec::new();
+    for activity in activities {
+        columns.push(period_totals(tracker_data, activity, start_date, end_date, Period::Week)?);
+    }
+    let num_weeks = columns[0].len();
+    let row_totals: Vec<u32> = (0..num_weeks).map(|i| columns.iter().map(|c| c[i].2).sum()).collect();
+    let max_total = row_totals.iter().copied().max().unwrap_or(0).max(1);
+    let width = LABEL_WIDTH + BAR_AREA_WIDTH + 50;
+    let height = num_weeks * (BAR_HEIGHT + BAR_GAP);
+    let mut svg = format!("<svg width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" role=\"img\">\n", width, height, width, height);
+    for i in 0..num_weeks {
+        let y = i * (BAR_HEIGHT + BAR_GAP);
+        let (week_start, week_end, _) = &columns[0][i];
+        svg.push_str(&format!("<text x=\"0\" y=\"{}\">{}</text>\n", y + BAR_HEIGHT - 5, week_start.to_string()));
+        // Segments end at the running total, so the stacked bar is as long as the week's total
+        let mut running_total: u32 = 0;
+        for (j, (activity, column)) in activities.iter().zip(&columns).enumerate() {
+            let minutes = column[i].2;
+            if minutes == 0 {
+                continue;
+            }
+            let x = LABEL_WIDTH + running_total as usize * BAR_AREA_WIDTH / max_total as usize;
+            running_total += minutes;
+            let end = LABEL_WIDTH + running_total as usize * BAR_AREA_WIDTH / max_total as usize;
+            svg.push_str(&format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"><title>{}: {} minutes from {} to {}</title></rect>\n",
+                                  x, y, end - x, BAR_HEIGHT, BAR_COLORS[j % BAR_COLORS.len()], escape(activity), minutes,
+                                  week_start.to_string(), week_end.to_string()));
+        }
+        let end = LABEL_WIDTH + running_total as usize * BAR_AREA_WIDTH / max_total as usize;
+        svg.push_str(&format!("<text x=\"{}\" y=\"{}\">{}</text>\n", end + 4, y + BAR_HEIGHT - 5, row_totals[i]));
+    }
+    svg.push_str("</svg>\n<ul class=\"legend\">\n");
+    for (j, activity) in activities.iter().enumerate() {
+        svg.push_str(&format!("<li><span class=\"swatch\" style=\"background: {}\"></span>{}</li>\n",
+                              BAR_COLORS[j % BAR_COLORS.len()], escape(activity)));
+    }
+    svg.push_str("</ul>\n");
+    Ok(svg)
+}
+
+// Returns a heatmap (as a heading and an SVG element) of a year of an activity, as a grid of weekdays (rows) by weeks
+// (columns) with month labels and a cell for each day from start_date to end_date, followed by a legend
+fn heatmap_svg(heatmap: &Heatmap, start_date: &Date, end_date: &Date) -> String {
+    let step = CELL_SIZE + CELL_GAP;
+    let width = WEEKDAY_LABEL_WIDTH + heatmap.weeks.len() * step;
+    let height = MONTH_LABEL_HEIGHT + WEEKDAY_NAMES.len() * step;
+    let mut svg = format!("<h3>{} in {}</h3>\n<p>{} minutes, at most {} on one day.</p>\n",
+                          escape(&heatmap.activity), heatmap.year, heatmap.total(), heatmap.max());
+    svg.push_str(&format!("<svg width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" role=\"img\">\n", width, height, width, height));
+    for (week, month) in heatmap.month_starts() {
+        svg.push_str(&format!("<text x=\"{}\" y=\"{}\">{}</text>\n", WEEKDAY_LABEL_WIDTH + week * step, MONTH_LABEL_HEIGHT - 5,
+                              MONTH_NAMES[month as usize - 1]));
+    }
+    for (weekday, name) in WEEKDAY_NAMES.iter().enumerate() {
+        svg.push_str(&format!("<text x=\"0\" y=\"{}\">{}</text>\n", MONTH_LABEL_HEIGHT + weekday * step + CELL_SIZE - 1, name));
+    }
+    for (week, days) in heatmap.weeks.iter().enumerate() {
+        for (weekday, day) in days.iter().enumerate() {
+            if let Some((date, minutes)) = day.as_ref().filter(|(date, _)| start_date <= date && date <= end_date) {
+                svg.push_str(&format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"><title>{}: {} minutes</title></rect>\n",
+                                      WEEKDAY_LABEL_WIDTH + week * step, MONTH_LABEL_HEIGHT + weekday * step, CELL_SIZE, CELL_SIZE,
+                                      HEATMAP_COLORS[heatmap.level(*minutes)], date.to_string(), minutes));
+            }
+        }
+    }
+    svg.push_str("</svg>\n<ul class=\"legend\">\n");
+    svg.push_str(&format!("<li><span class=\"swatch\" style=\"background: {}\"></span>0 minutes</li>\n", HEATMAP_COLORS[0]));
+    for (level, low, high) in heatmap.level_ranges() {
+        svg.push_str(&format!("<li><span class=\"swatch\" style=\"background: {}\"></span>{}-{} minutes</li>\n",
+                              HEATMAP_COLORS[level], low, high));
+    }
+    svg.push_str("</ul>\n");
+    svg
+}
+
+// Returns text with the characters that have a meaning in HTML replaced by their character references
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::html_report::{html_report, escape};
+    use crate::tracker_data::TrackerData;
+    use crate::date::Date;
+
+    fn date(date_str: &str) -> Date {
+        Date::new_from_string(date_str).unwrap()
+    }
+
+    #[test]
+    fn report() {
+        let mut tracker_data = TrackerData::new();
+        for (d, activity, minutes) in [("2023-6-1", "guitar", 30), ("2023-6-1", "work/<meetings>", 90), ("2023-6-6", "guitar", 60),
+                                        ("2023-7-1", "guitar", 600)] {
+            assert_eq!(tracker_data.add(date(d), String::from(activity), minutes), Ok(()));
+        }
+        let categories = HashMap::from([(String::from("work/<meetings>"), String::from("work"))]);
+        let html = html_report(&tracker_data, &date("2023-6-1"), &date("2023-6-11"), &categories).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        // Everything is inline, so the page never loads anything from the network
+        assert!(!html.contains("http") && !html.contains("src=") && !html.contains("<link"));
+        assert!(html.contains("<tr><td>guitar</td><td>90</td><td>45</td><td>50.0%</td></tr>"));
+        assert!(html.contains("<tr><td>work/&lt;meetings&gt;</td><td>90</td><td>45</td><td>50.0%</td></tr>"));
+        assert!(html.contains("<tr><td>work</td><td>90</td><td>45</td><td>50.0%</td></tr>\n</table>"));
+        assert!(html.contains("<tr class=\"total\"><td>Total</td><td>180</td><td>90</td><td>100.0%</td></tr>"));
+        // The weeks are cut off at the start of the range, and the longest week fills the bar area
+        assert!(html.contains("<text x=\"0\" y=\"13\">2023-6-1</text>"));
+        assert!(html.contains("<text x=\"0\" y=\"37\">2023-6-5</text>"));
+        assert!(html.contains("<rect x=\"90\" y=\"0\" width=\"130\" height=\"18\" fill=\"#4e79a7\"><title>guitar: 30 minutes from 2023-6-1 to 2023-6-4</title></rect>"));
+        assert!(html.contains("<text x=\"614\" y=\"13\">120</text>"));
+        // Every activity gets a heatmap with a cell for every day of the range, and only the time in the range counts
+        assert!(html.contains("<h3>guitar in 2023</h3>\n<p>90 minutes, at most 60 on one day.</p>"));
+        assert!(html.contains("<h3>work/&lt;meetings&gt; in 2023</h3>"));
+        assert_eq!(html.matches("minutes</title></rect>").count(), 2 * 11);
+        assert!(html.contains("<title>2023-6-6: 60 minutes</title>"));
+        assert!(!html.contains("<title>2023-7-1: 600 minutes</title>"));
+
+        assert_eq!(html_report(&tracker_data, &date("2023-8-1"), &date("2023-8-2"), &categories),
+                    Err(String::from("Report error: no data for 2023-8-1 to 2023-8-2")));
+        assert!(html_report(&tracker_data, &date("2023-6-2"), &date("2023-6-1"), &categories).is_err());
+        assert_eq!(escape("a & \"b\" <c>"), String::from("a &amp; &quot;b&quot; &lt;c&gt;"));
+    }
+}
@@ -0,0 +1,307 @@
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+use crate::date::Date;
+use crate::importers::{split_at_midnight, ImportedRows};
+use crate::tracker_data::TrackerData;
+
+// The longest a line may be (in bytes, not counting the line break) before it is folded onto the next line
+const MAX_LINE_LENGTH: usize = 75;
+
+// A property of an event: its name, its parameters (such as "VALUE=DATE"), and its value
+type Property = (String, String, String);
+
+// Returns the tracker data (only from start_date to end_date, if there is a range) as an iCalendar file with one
+// all-day event per activity on each date
+pub fn to_ics(tracker_data: &TrackerData, range: Option<(Date, Date)>) -> String {
+    to_ics_at(tracker_data, range, &Utc::now().format("%Y%m%dT%H%M%SZ").to_string())
+}
+
+// Returns the rows in an iCalendar file, with each timed event's minutes split between the dates it covers
+// Events are named by their summary, which can be mapped to an activity with mappings such as ("Gym", "exercise")
+// (when there are mappings, events without one are skipped)
+// Times are read in UTC or the local time zone, so events with times in another time zone (TZID) are skipped too
+pub fn from_ics(contents: &str, mappings: &[(String, String)]) -> ImportedRows {
+    from_ics_in(contents, mappings, &Local)
+}
+
+// Returns the tracker data as an iCalendar file, stamping every event with dtstamp
+fn to_ics_at(tracker_data: &TrackerData, range: Option<(Date, Date)>, dtstamp: &str) -> String {
+    let mut lines: Vec<String> = vec![
+        String::from("BEGIN:VCALENDAR"),
+        String::from("VERSION:2.0"),
+        String::from("PRODID:-//tracker//tracker//EN"),
+    ];
+    for (date, activity, minutes) in tracker_data.entries() {
+        if let Some((start_date, end_date)) = &range {
+            if &date < start_date || &date > end_date {
+                continue;
+            }
+        }
+        let next_date = date.add_days(1).unwrap_or(date.clone());
+        lines.push(String::from("BEGIN:VEVENT"));
+        lines.push(format!("UID:{}-{}@tracker", date.to_iso_string(), escape_text(&activity)));
+        lines.push(format!("DTSTAMP:{}", dtstamp));
+        lines.push(format!("DTSTART;VALUE=DATE:{}", date.to_iso_string().replace('-', "")));
+        lines.push(format!("DTEND;VALUE=DATE:{}", next_date.to_iso_string().replace('-', "")));
+        lines.push(format!("SUMMARY:{} ({} min)", escape_text(&activity), minutes));
+        lines.push(String::from("TRANSP:TRANSPARENT"));
+        lines.push(format!("X-TRACKER-ACTIVITY:{}", escape_text(&activity)));
+        lines.push(format!("X-TRACKER-MINUTES:{}", minutes));
+        lines.push(String::from("END:VEVENT"));
+    }
+    lines.push(String::from("END:VCALENDAR"));
+    lines.iter().map(|l| fold_line(l)).collect::<Vec<String>>().join("")
+}
+
+// Returns the rows in an iCalendar file, reading times without a time zone as being in the time zone tz
+fn from_ics_in<Tz: TimeZone>(contents: &str, mappings: &[(String, String)], tz: &Tz) -> ImportedRows {
+    let mut rows: Vec<(Date, String, u16)> = Vec::new();
+    let mut skipped: Vec<String> = Vec::new();
+    // The line the event being read began on and its properties
+    let mut event: Option<(usize, Vec<Property>)> = None;
+    for (line_number, line) in unfold_lines(contents) {
+        let (name_params, value) = match line.split_once(':') {
+            Some(nv) => nv,
+            None => continue,
+        };
+        let (name, params) = match name_params.split_once(';') {
+            Some((n, p)) => (n.to_uppercase(), p.to_uppercase()),
+            None => (name_params.to_uppercase(), String::new()),
+        };
+        match (name.as_str(), value.trim().to_uppercase().as_str()) {
+            ("BEGIN", "VEVENT") => event = Some((line_number, Vec::new())),
+            ("END", "VEVENT") => {
+                if let Some((begin_line, properties)) = event.take() {
+                    if let Err(e) = push_event(&mut rows, &properties, mappings, tz) {
+                        skipped.push(format!("line {}: {}", begin_line, e));
+                    }
+                }
+            }
+            _ => {
+                if let Some((_, properties)) = &mut event {
+                    properties.push((name, params, value.to_string()));
+                }
+            }
+        }
+    }
+    (rows, skipped)
+}
+
+// Adds the rows for an event with the given properties, or returns why the event cannot be imported
+fn push_event<Tz: TimeZone>(rows: &mut Vec<(Date, String, u16)>, properties: &[Property],
+                            mappings: &[(String, String)], tz: &Tz) -> Result<(), String> {
+    let property = |name: &str| properties.iter().find(|(n, _, _)| n == name).map(|(_, p, v)| (p.as_str(), v.trim()));
+    if property("RRULE").is_some() {
+        return Err(String::from("recurring events are not supported"));
+    }
+    // Events exported by tracker keep their exact activity and minutes
+    let exported = match (property("X-TRACKER-ACTIVITY"), property("X-TRACKER-MINUTES")) {
+        (Some((_, activity)), Some((_, minutes))) => Some((unescape_text(activity), minutes.parse::<u16>().ok())),
+        _ => None,
+    };
+    let name = match (&exported, property("SUMMARY")) {
+        (Some((activity, _)), _) => activity.clone(),
+        (None, Some((_, summary))) => unescape_text(summary),
+        (None, None) => return Err(String::from("event has no summary")),
+    };
+    let activity = if mappings.is_empty() {
+        name.clone()
+    } else {
+        match mappings.iter().find(|(from, _)| from.eq_ignore_ascii_case(&name)) {
+            Some((_, to)) => to.clone(),
+            None => return Err(format!("event \"{}\" is not mapped to an activity", name)),
+        }
+    };
+    if activity.is_empty() {
+        return Err(String::from("event has no summary"));
+    }
+    let (start_params, start_value) = property("DTSTART").ok_or(String::from("event has no start"))?;
+    if let Some((_, Some(minutes))) = exported {
+        let date = ics_date(start_value).ok_or(String::from("cannot interpret start"))?;
+        rows.push((date, activity, minutes));
+        return Ok(());
+    }
+    if start_params.contains("VALUE=DATE") && !start_params.contains("VALUE=DATE-TIME") {
+        return Err(String::from("all-day event has no duration"));
+    }
+    // Times in a named time zone would need a time zone database to convert, so they are not guessed at
+    let end_params = property("DTEND").map_or("", |(p, _)| p);
+    if start_params.contains("TZID=") || end_params.contains("TZID=") {
+        return Err(String::from("times in a named time zone (TZID) are not supported"));
+    }
+    let start = ics_date_time(start_value, tz).ok_or(String::from("cannot interpret start"))?;
+    let end = match (property("DTEND"), property("DURATION")) {
+        (Some((_, end_value)), _) => ics_date_time(end_value, tz).ok_or(String::from("cannot interpret end"))?,
+        (None, Some((_, duration))) => start + ics_duration(duration).ok_or(String::from("cannot interpret duration"))?,
+        (None, None) => return Err(String::from("event has no end")),
+    };
+    if end <= start {
+        return Err(String::from("end is not after start"));
+    }
+    for (date, minutes) in split_at_midnight(start, end) {
+        rows.push((date, activity.clone(), minutes));
+    }
+    Ok(())
+}
+
+// Returns the Date in an iCalendar date such as "20230601"
+fn ics_date(value: &str) -> Option<Date> {
+    let date = NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok()?;
+    Date::new_from_ints(date.year() as u16, date.month() as u16, date.day() as u16).ok()
+}
+
+// Returns the local time in an iCalendar date-time such as "20230601T090000Z" (UTC) or "20230601T090000" (local)
+fn ics_date_time<Tz: TimeZone>(value: &str, tz: &Tz) -> Option<NaiveDateTime> {
+    match value.strip_suffix('Z') {
+        Some(utc) => NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()
+            .map(|naive| tz.from_utc_datetime(&naive).naive_local()),
+        None => NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok(),
+    }
+}
+
+// Returns the length of an iCalendar duration such as "PT1H30M" or "P1DT2H"
+fn ics_duration(value: &str) -> Option<chrono::Duration> {
+    let mut seconds: i64 = 0;
+    let mut number = String::new();
+    for c in value.strip_prefix('P')?.chars() {
+        let unit = match c {
+            '0'..='9' => {
+                number.push(c);
+                continue;
+            }
+            'T' => continue,
+            'W' => 7 * 24 * 60 * 60,
+            'D' => 24 * 60 * 60,
+            'H' => 60 * 60,
+            'M' => 60,
+            'S' => 1,
+            _ => return None,
+        };
+        seconds += number.parse::<i64>().ok()? * unit;
+        number.clear();
+    }
+    if number.is_empty() {Some(chrono::Duration::seconds(seconds))} else {None}
+}
+
+// Joins folded lines (continuation lines start with a space or tab), returning each line with its line number
+fn unfold_lines(contents: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        match (line.strip_prefix(' ').or(line.strip_prefix('\t')), lines.last_mut()) {
+            (Some(continuation), Some((_, last))) => last.push_str(continuation),
+            _ => lines.push((i + 1, line.to_string())),
+        }
+    }
+    lines
+}
+
+// Splits a line into pieces of at most MAX_LINE_LENGTH bytes (without splitting a character), each ending in CRLF
+fn fold_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_LENGTH {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+// Escapes backslashes, semicolons, commas, and newlines in iCalendar text
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\").replace(';', "\\;").replace(',', "\\,").replace('\n', "\\n")
+}
+
+// Reverses escape_text
+fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(escaped) => unescaped.push(escaped),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use crate::ical::{to_ics_at, from_ics_in};
+    use crate::tracker_data::TrackerData;
+    use crate::date::Date;
+
+    // Returns the Date for a date string (keeps the expected rows short)
+    fn date(date_str: &str) -> Date {
+        Date::new_from_string(date_str).unwrap()
+    }
+
+    #[test]
+    fn export() {
+        let mut tracker_data = TrackerData::new();
+        assert_eq!(tracker_data.add(date("2023-6-30"), String::from("guitar, electric"), 30), Ok(()));
+        assert_eq!(tracker_data.add(date("2023-7-1"), String::from("school"), 180), Ok(()));
+        let ics = to_ics_at(&tracker_data, Some((date("2023-6-1"), date("2023-6-30"))), "20231018T120000Z");
+        assert_eq!(ics, String::from("BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//tracker//tracker//EN\r\n\
+BEGIN:VEVENT\r\nUID:2023-06-30-guitar\\, electric@tracker\r\nDTSTAMP:20231018T120000Z\r\n\
+DTSTART;VALUE=DATE:20230630\r\nDTEND;VALUE=DATE:20230701\r\nSUMMARY:guitar\\, electric (30 min)\r\n\
+TRANSP:TRANSPARENT\r\nX-TRACKER-ACTIVITY:guitar\\, electric\r\nX-TRACKER-MINUTES:30\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n"));
+
+        // Exported events round trip, and long lines are folded
+        let long_activity = "a".repeat(100);
+        assert_eq!(tracker_data.add(date("2023-7-2"), long_activity.clone(), 15), Ok(()));
+        let ics = to_ics_at(&tracker_data, None, "20231018T120000Z");
+        assert!(ics.lines().all(|l| l.len() <= 76));
+        assert_eq!(from_ics_in(&ics, &[], &Utc), (tracker_data.entries(), Vec::new()));
+    }
+
+    #[test]
+    fn import() {
+        let contents = "BEGIN:VCALENDAR
+BEGIN:VEVENT
+SUMMARY:Gym
+DTSTART:20230601T230000Z
+DTEND:20230602T003000Z
+END:VEVENT
+BEGIN:VEVENT
+SUMMARY:gym
+DTSTART;TZID=Europe/Paris:20230603T090000
+DURATION:PT45M
+END:VEVENT
+BEGIN:VEVENT
+SUMMARY:Holiday
+DTSTART;VALUE=DATE:20230604
+END:VEVENT
+BEGIN:VEVENT
+SUMMARY:Team meeting
+DTSTART:20230605T090000Z
+DTEND:20230605T100000Z
+END:VEVENT
+END:VCALENDAR";
+        // Without mappings, events are named by their summary
+        let (rows, skipped) = from_ics_in(contents, &[], &Utc);
+        assert_eq!(rows, vec![(date("2023-6-1"), String::from("Gym"), 60),
+                              (date("2023-6-2"), String::from("Gym"), 30),
+                              (date("2023-6-5"), String::from("Team meeting"), 60)]);
+        assert_eq!(skipped, vec![String::from("line 7: times in a named time zone (TZID) are not supported"),
+                                 String::from("line 12: all-day event has no duration")]);
+
+        // With mappings, only mapped events are imported
+        let (rows, skipped) = from_ics_in(contents, &[(String::from("Gym"), String::from("exercise"))], &Utc);
+        assert_eq!(rows, vec![(date("2023-6-1"), String::from("exercise"), 60),
+                              (date("2023-6-2"), String::from("exercise"), 30)]);
+        assert_eq!(skipped, vec![String::from("line 7: times in a named time zone (TZID) are not supported"),
+                                 String::from("line 12: event \"Holiday\" is not mapped to an activity"),
+                                 String::from("line 16: event \"Team meeting\" is not mapped to an activity")]);
+    }
+}
@@ -0,0 +1,291 @@
+use chrono::{Datelike, Local, NaiveDateTime, TimeZone};
+
+use crate::csv::split_fields;
+use crate::date::Date;
+
+// The (date, activity, minutes) rows found by an importer, along with a description of every line it skipped
+pub type ImportedRows = (Vec<(Date, String, u16)>, Vec<String>);
+
+// Splits the interval from start to end at midnight, returning the minutes (rounded to the nearest minute) spent on each date
+pub fn split_at_midnight(start: NaiveDateTime, end: NaiveDateTime) -> Vec<(Date, u16)> {
+    let mut minutes_per_date: Vec<(Date, u16)> = Vec::new();
+    let mut curr = start;
+    while curr < end {
+        let next_midnight = match curr.date().succ_opt().and_then(|d| d.and_hms_opt(0, 0, 0)) {
+            Some(m) => m,
+            None => break,
+        };
+        let segment_end = if end < next_midnight {end} else {next_midnight};
+        let minutes = ((segment_end - curr).num_seconds() + 30) / 60;
+        if minutes > 0 {
+            let date = curr.date();
+            match Date::new_from_ints(date.year() as u16, date.month() as u16, date.day() as u16) {
+                Ok(d) => minutes_per_date.push((d, minutes as u16)),
+                Err(_) => break,
+            }
+        }
+        curr = segment_end;
+    }
+    minutes_per_date
+}
+
+// Returns the rows in a Timewarrior data file (such as "2023-06.data"), using the first tag of each interval as the activity
+// Timewarrior stores times in UTC, so they are converted to local time before being split into dates
+pub fn from_timewarrior(contents: &str) -> ImportedRows {
+    from_timewarrior_in(contents, &Local)
+}
+
+// Returns the rows in a Toggl CSV export, using the project (or the description, if there is no project) as the activity
+pub fn from_toggl_csv(contents: &str) -> Result<ImportedRows, String> {
+    let mut lines = contents.lines().enumerate();
+    // Find the columns we need in the header
+    let header = match lines.next() {
+        Some((_, line)) => split_fields(line).unwrap_or_default(),
+        None => return Ok((Vec::new(), Vec::new())),
+    };
+    let column = |name: &str| header.iter().position(|h| h.trim() == name)
+        .ok_or(format!("Toggl error: missing \"{}\" column", name));
+    let (project, description) = (column("Project")?, column("Description")?);
+    let (start_date, start_time) = (column("Start date")?, column("Start time")?);
+    let (end_date, end_time) = (column("End date")?, column("End time")?);
+    // Convert each entry into rows
+    let mut rows: Vec<(Date, String, u16)> = Vec::new();
+    let mut skipped: Vec<String> = Vec::new();
+    for (i, line) in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = match split_fields(line) {
+            Some(f) if f.len() == header.len() => f,
+            _ => {
+                skipped.push(format!("line {}: expected {} fields", i + 1, header.len()));
+                continue;
+            }
+        };
+        let activity = if fields[project].trim().is_empty() {fields[description].trim()} else {fields[project].trim()};
+        if activity.is_empty() {
+            skipped.push(format!("line {}: no project or description", i + 1));
+            continue;
+        }
+        let start = NaiveDateTime::parse_from_str(&format!("{} {}", fields[start_date].trim(), fields[start_time].trim()), "%Y-%m-%d %H:%M:%S");
+        let end = NaiveDateTime::parse_from_str(&format!("{} {}", fields[end_date].trim(), fields[end_time].trim()), "%Y-%m-%d %H:%M:%S");
+        match (start, end) {
+            (Ok(s), Ok(e)) if s < e => push_interval(&mut rows, activity, s, e),
+            (Ok(_), Ok(_)) => skipped.push(format!("line {}: end is not after start", i + 1)),
+            _ => skipped.push(format!("line {}: cannot interpret start or end time", i + 1)),
+        }
+    }
+    Ok((rows, skipped))
+}
+
+// Returns the rows in the CLOCK lines of an org-mode file, using the heading each CLOCK line belongs to as the activity
+pub fn from_org_clock(contents: &str) -> ImportedRows {
+    let mut rows: Vec<(Date, String, u16)> = Vec::new();
+    let mut skipped: Vec<String> = Vec::new();
+    let mut heading: Option<String> = None;
+    for (i, line) in contents.lines().enumerate() {
+        let trimmed = line.trim();
+        if line.starts_with('*') {
+            heading = org_heading(line);
+            continue;
+        }
+        let clock = match trimmed.strip_prefix("CLOCK:") {
+            Some(c) => c.trim(),
+            None => continue,
+        };
+        let activity = match &heading {
+            Some(h) => h.clone(),
+            None => {
+                skipped.push(format!("line {}: CLOCK line is not under a heading", i + 1));
+                continue;
+            }
+        };
+        let (start, end) = match clock.split_once("--") {
+            Some((s, e)) => (org_timestamp(s), org_timestamp(e.split("=>").next().unwrap_or(""))),
+            None => {
+                skipped.push(format!("line {}: clock is still running", i + 1));
+                continue;
+            }
+        };
+        match (start, end) {
+            (Some(s), Some(e)) if s < e => push_interval(&mut rows, &activity, s, e),
+            (Some(_), Some(_)) => skipped.push(format!("line {}: end is not after start", i + 1)),
+            _ => skipped.push(format!("line {}: cannot interpret timestamps", i + 1)),
+        }
+    }
+    (rows, skipped)
+}
+
+// Returns the rows in a Timewarrior data file, converting times from UTC into the time zone tz
+fn from_timewarrior_in<Tz: TimeZone>(contents: &str, tz: &Tz) -> ImportedRows {
+    let mut rows: Vec<(Date, String, u16)> = Vec::new();
+    let mut skipped: Vec<String> = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        // Lines look like: inc 20230601T090000Z - 20230601T100000Z # tag "another tag" # annotation
+        let interval = match line.strip_prefix("inc ") {
+            Some(rest) => rest,
+            None => {
+                skipped.push(format!("line {}: not an interval", i + 1));
+                continue;
+            }
+        };
+        let (times, tags) = match interval.split_once(" # ") {
+            Some((t, rest)) => (t.trim(), rest.split(" # ").next().unwrap_or("")),
+            None => (interval.trim(), ""),
+        };
+        let activity = match timewarrior_tags(tags).into_iter().next() {
+            Some(tag) => tag,
+            None => {
+                skipped.push(format!("line {}: interval has no tags", i + 1));
+                continue;
+            }
+        };
+        let (start, end) = match times.split_once(" - ") {
+            Some((s, e)) => (s.trim(), e.trim()),
+            None => {
+                skipped.push(format!("line {}: interval is still open", i + 1));
+                continue;
+            }
+        };
+        let to_local = |t: &str| NaiveDateTime::parse_from_str(t, "%Y%m%dT%H%M%SZ").ok()
+            .map(|naive| tz.from_utc_datetime(&naive).naive_local());
+        match (to_local(start), to_local(end)) {
+            (Some(s), Some(e)) if s < e => push_interval(&mut rows, &activity, s, e),
+            (Some(_), Some(_)) => skipped.push(format!("line {}: end is not after start", i + 1)),
+            _ => skipped.push(format!("line {}: cannot interpret timestamps", i + 1)),
+        }
+    }
+    (rows, skipped)
+}
+
+// Adds a row for each date the interval from start to end covers
+fn push_interval(rows: &mut Vec<(Date, String, u16)>, activity: &str, start: NaiveDateTime, end: NaiveDateTime) {
+    for (date, minutes) in split_at_midnight(start, end) {
+        rows.push((date, activity.to_string(), minutes));
+    }
+}
+
+// Splits Timewarrior tags on spaces, keeping quoted tags together
+fn timewarrior_tags(tags: &str) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    let mut tag = String::new();
+    let mut in_quotes = false;
+    for c in tags.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ' ' if !in_quotes => {
+                if !tag.is_empty() {
+                    result.push(std::mem::take(&mut tag));
+                }
+            }
+            c => tag.push(c),
+        }
+    }
+    if !tag.is_empty() {
+        result.push(tag);
+    }
+    result
+}
+
+// Returns the title of an org-mode heading, without its stars, TODO keyword, priority, or tags
+fn org_heading(line: &str) -> Option<String> {
+    let mut words: Vec<&str> = line.trim_start_matches('*').split_whitespace().collect();
+    if words.first().is_some_and(|w| ["TODO", "DONE", "NEXT", "WAITING", "CANCELLED"].contains(w)) {
+        words.remove(0);
+    }
+    if words.first().is_some_and(|w| w.starts_with("[#") && w.ends_with(']')) {
+        words.remove(0);
+    }
+    if words.last().is_some_and(|w| w.len() > 1 && w.starts_with(':') && w.ends_with(':')) {
+        words.pop();
+    }
+    if words.is_empty() {None} else {Some(words.join(" "))}
+}
+
+// Returns the time in an org-mode timestamp such as "[2023-06-01 Thu 09:00]"
+fn org_timestamp(timestamp: &str) -> Option<NaiveDateTime> {
+    let inner = timestamp.trim().strip_prefix('[')?.strip_suffix(']')?;
+    let words: Vec<&str> = inner.split_whitespace().collect();
+    if words.len() < 2 {
+        return None;
+    }
+    NaiveDateTime::parse_from_str(&format!("{} {}", words[0], words[words.len() - 1]), "%Y-%m-%d %H:%M").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDateTime, Utc};
+    use crate::importers::{split_at_midnight, from_timewarrior_in, from_toggl_csv, from_org_clock};
+    use crate::date::Date;
+
+    // Returns the Date for a date string (keeps the expected rows short)
+    fn date(date_str: &str) -> Date {
+        Date::new_from_string(date_str).unwrap()
+    }
+
+    #[test]
+    fn splitting() {
+        let time = |t: &str| NaiveDateTime::parse_from_str(t, "%Y-%m-%d %H:%M:%S").unwrap();
+        assert_eq!(split_at_midnight(time("2023-06-01 09:00:00"), time("2023-06-01 10:30:00")),
+                    vec![(date("2023-6-1"), 90)]);
+        assert_eq!(split_at_midnight(time("2023-06-30 23:00:00"), time("2023-07-02 00:15:00")),
+                    vec![(date("2023-6-30"), 60), (date("2023-7-1"), 1440), (date("2023-7-2"), 15)]);
+        assert_eq!(split_at_midnight(time("2023-06-01 09:00:00"), time("2023-06-01 09:00:20")),
+                    vec![]);
+    }
+
+    #[test]
+    fn timewarrior() {
+        let contents = "inc 20230601T230000Z - 20230602T003000Z # guitar \"music practice\"
+inc 20230602T090000Z - 20230602T100000Z # \"deep work\" # planning the week
+inc 20230602T110000Z - 20230602T120000Z
+inc 20230603T090000Z # guitar
+hello";
+        let (rows, skipped) = from_timewarrior_in(contents, &Utc);
+        assert_eq!(rows, vec![(date("2023-6-1"), String::from("guitar"), 60),
+                              (date("2023-6-2"), String::from("guitar"), 30),
+                              (date("2023-6-2"), String::from("deep work"), 60)]);
+        assert_eq!(skipped, vec![String::from("line 3: interval has no tags"),
+                                 String::from("line 4: interval is still open"),
+                                 String::from("line 5: not an interval")]);
+    }
+
+    #[test]
+    fn toggl() {
+        let contents = "User,Email,Client,Project,Task,Description,Billable,Start date,Start time,End date,End time,Duration,Tags
+Pat,pat@example.com,,work,,\"emails, mostly\",No,2023-06-01,09:00:00,2023-06-01,09:45:00,00:45:00,
+Pat,pat@example.com,,,,reading,No,2023-06-01,23:30:00,2023-06-02,00:30:00,01:00:00,
+Pat,pat@example.com,,,,,No,2023-06-02,09:00:00,2023-06-02,10:00:00,01:00:00,
+Pat,pat@example.com,,work,,,No,2023-06-02,11:00:00,2023-06-02,10:00:00,-01:00:00,";
+        let (rows, skipped) = from_toggl_csv(contents).unwrap();
+        assert_eq!(rows, vec![(date("2023-6-1"), String::from("work"), 45),
+                              (date("2023-6-1"), String::from("reading"), 30),
+                              (date("2023-6-2"), String::from("reading"), 30)]);
+        assert_eq!(skipped, vec![String::from("line 4: no project or description"),
+                                 String::from("line 5: end is not after start")]);
+        assert_eq!(from_toggl_csv("Project,Description\nwork,emails"),
+                    Err(String::from("Toggl error: missing \"Start date\" column")));
+    }
+
+    #[test]
+    fn org() {
+        let contents = "CLOCK: [2023-06-01 Thu 08:00]--[2023-06-01 Thu 08:30] =>  0:30
+* TODO [#A] Guitar practice :music:
+  :LOGBOOK:
+  CLOCK: [2023-06-01 Thu 09:00]--[2023-06-01 Thu 10:30] =>  1:30
+  CLOCK: [2023-06-02 Fri 09:00]
+  :END:
+** Reading
+  CLOCK: [2023-06-01 Thu 23:00]--[2023-06-02 Fri 01:00] =>  2:00";
+        let (rows, skipped) = from_org_clock(contents);
+        assert_eq!(rows, vec![(date("2023-6-1"), String::from("Guitar practice"), 90),
+                              (date("2023-6-1"), String::from("Reading"), 60),
+                              (date("2023-6-2"), String::from("Reading"), 60)]);
+        assert_eq!(skipped, vec![String::from("line 1: CLOCK line is not under a heading"),
+                                 String::from("line 5: clock is still running")]);
+    }
+}
@@ -0,0 +1,286 @@
+use std::fs::File;
+use std::io::prelude::*;
+use chrono::Local;
+use json::JsonValue;
+
+use crate::date::Date;
+use crate::encryption::{self, Cipher};
+use crate::tracker_data::TrackerData;
+
+// Struct Definitions
+// A change to the minutes of one activity on one date (None means no time recorded)
+#[derive(Debug, PartialEq, Clone)]
+pub struct Change {
+    pub date: Date,
+    pub activity: String,
+    pub before: Option<u16>,
+    pub after: Option<u16>,
+}
+
+// A single mutating command along with the changes it made
+#[derive(Debug, PartialEq, Clone)]
+pub struct JournalEntry {
+    pub timestamp: String,
+    pub description: String,
+    pub changes: Vec<Change>,
+}
+
+// The applied operations (oldest first) and the undone operations that can be redone (most recently undone last)
+// Like the data file, the journal is encrypted again when saved if it was encrypted when loaded
+pub struct Journal {
+    entries: Vec<JournalEntry>,
+    undone: Vec<JournalEntry>,
+    cipher: Option<Cipher>,
+}
+
+// Public Methods
+impl JournalEntry {
+    // Creates a new JournalEntry timestamped with the current time
+    pub fn new(description: &str, changes: Vec<Change>) -> JournalEntry {
+        JournalEntry {
+            timestamp: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            description: description.to_string(),
+            changes,
+        }
+    }
+
+    // Returns a JsonValue object representing this JournalEntry
+    pub fn to_json(self: &Self) -> JsonValue {
+        let mut changes_json = JsonValue::new_array();
+        for change in &self.changes {
+            changes_json.push(json::object!{
+                date: change.date.to_string(),
+                activity: change.activity.clone(),
+                before: change.before,
+                after: change.after
+            }).unwrap();
+        }
+        json::object!{
+            time: self.timestamp.clone(),
+            description: self.description.clone(),
+            changes: changes_json
+        }
+    }
+
+    // Creates a new JournalEntry from a JsonValue object
+    pub fn from_json(entry_json: &JsonValue) -> Result<JournalEntry, String> {
+        let error = String::from("From JSON error: JSON cannot be interpreted as JournalEntry");
+        let timestamp = entry_json["time"].as_str().ok_or(error.clone())?;
+        let description = entry_json["description"].as_str().ok_or(error.clone())?;
+        let mut changes: Vec<Change> = Vec::new();
+        for change_json in entry_json["changes"].members() {
+            let date = change_json["date"].as_str()
+                .and_then(|d| Date::new_from_string(d).ok())
+                .ok_or(error.clone())?;
+            let activity = change_json["activity"].as_str().ok_or(error.clone())?;
+            changes.push(Change {
+                date,
+                activity: activity.to_string(),
+                before: change_json["before"].as_u16(),
+                after: change_json["after"].as_u16(),
+            });
+        }
+        Ok(JournalEntry {
+            timestamp: timestamp.to_string(),
+            description: description.to_string(),
+            changes,
+        })
+    }
+}
+
+impl Change {
+    // Returns a description (as a String) of this Change, such as "2023-6-1 guitar: 30 -> 45"
+    pub fn describe(self: &Self) -> String {
+        let minutes_str = |minutes: Option<u16>| match minutes {
+            Some(m) => m.to_string(),
+            None => String::from("none"),
+        };
+        format!("{} {}: {} -> {}", self.date.to_string(), self.activity, minutes_str(self.before), minutes_str(self.after))
+    }
+}
+
+impl Journal {
+    // Creates a new, empty Journal
+    pub fn new() -> Journal {
+        Journal {
+            entries: Vec::new(),
+            undone: Vec::new(),
+            cipher: None,
+        }
+    }
+
+    // Loads JSON formatted data from file filename into this Journal
+    pub fn load_from_file(self: &mut Self, filename: &str) -> Result<(), String> {
+        // Open the file and get the contents
+        let mut contents = String::new();
+        if let Ok(mut file) = File::open(filename) {
+            match file.read_to_string(&mut contents) {
+                Ok(_) => {}
+                Err(_) => {return Err(format!("Load from file error: cannot read \"{filename}\""));}
+            }
+        }
+        // Decrypt the contents if the file is encrypted (with the current cipher if it has the same key, and keeping the
+        // cipher of the file), then parse them and use them to populate this Journal
+        // A missing or plaintext file keeps the current cipher, so the file of an encrypted profile is encrypted when saved
+        let (contents, cipher) = encryption::decrypt_if_encrypted(&contents, &self.cipher, filename)?;
+        if cipher.is_some() {
+            self.cipher = cipher;
+        }
+        if !contents.is_empty() {
+            match json::parse(&contents) {
+                Ok(parsed) => {
+                    let mut entries: Vec<JournalEntry> = Vec::new();
+                    for entry_json in parsed["entries"].members() {
+                        entries.push(JournalEntry::from_json(entry_json)?);
+                    }
+                    let mut undone: Vec<JournalEntry> = Vec::new();
+                    for entry_json in parsed["undone"].members() {
+                        undone.push(JournalEntry::from_json(entry_json)?);
+                    }
+                    self.entries = entries;
+                    self.undone = undone;
+                }
+                Err(_) => {
+                    return Err(format!("Load from file error: cannot parse contents of \"{filename}\""));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Saves JSON formatted data from this Journal into the file filename
+    pub fn save_to_file(self: &Self, filename: &str) -> Result<(), String> {
+        let journal_json = json::object!{
+            entries: self.entries.iter().map(|e| e.to_json()).collect::<Vec<JsonValue>>(),
+            undone: self.undone.iter().map(|e| e.to_json()).collect::<Vec<JsonValue>>()
+        };
+        match File::create(filename) {
+            Ok(mut file) => {
+                match file.write_all(encryption::encrypt_if(&self.cipher, journal_json.pretty(4))?.as_bytes()) {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err(format!("Save to file error: cannot write to \"{filename}\"")),
+                }
+            }
+            Err(_) => Err(format!("Save to file error: cannot open \"{filename}\"")),
+        }
+    }
+
+    // Changes how this Journal is encrypted when saved (None saves it unencrypted)
+    pub fn set_cipher(self: &mut Self, cipher: Option<Cipher>) {
+        self.cipher = cipher;
+    }
+
+    // Records a new operation (which means the undone operations can no longer be redone)
+    pub fn record(self: &mut Self, entry: JournalEntry) {
+        if !entry.changes.is_empty() {
+            self.entries.push(entry);
+            self.undone.clear();
+        }
+    }
+
+    // Reverts the last count operations on the tracker data, returning the undone operations (most recent first)
+    pub fn undo(self: &mut Self, tracker_data: &mut TrackerData, count: usize) -> Result<Vec<JournalEntry>, String> {
+        if self.entries.is_empty() {
+            return Err(String::from("Undo error: nothing to undo"));
+        }
+        let mut undone: Vec<JournalEntry> = Vec::new();
+        for _ in 0..count {
+            match self.entries.pop() {
+                Some(entry) => {
+                    tracker_data.apply_changes(&entry.changes, true);
+                    undone.push(entry.clone());
+                    self.undone.push(entry);
+                }
+                None => break,
+            }
+        }
+        Ok(undone)
+    }
+
+    // Reapplies the last count undone operations on the tracker data, returning the redone operations (oldest first)
+    pub fn redo(self: &mut Self, tracker_data: &mut TrackerData, count: usize) -> Result<Vec<JournalEntry>, String> {
+        if self.undone.is_empty() {
+            return Err(String::from("Redo error: nothing to redo"));
+        }
+        let mut redone: Vec<JournalEntry> = Vec::new();
+        for _ in 0..count {
+            match self.undone.pop() {
+                Some(entry) => {
+                    tracker_data.apply_changes(&entry.changes, false);
+                    redone.push(entry.clone());
+                    self.entries.push(entry);
+                }
+                None => break,
+            }
+        }
+        Ok(redone)
+    }
+
+    // Returns a list (as a String) of the last count operations with their timestamps (most recent first)
+    pub fn history(self: &Self, count: usize) -> Result<String, String> {
+        if self.entries.is_empty() && self.undone.is_empty() {
+            return Err(String::from("History error: no operations recorded"));
+        }
+        let mut history = String::from("TIME\t\t\tOPERATION\n");
+        for entry in self.entries.iter().rev().take(count) {
+            history.push_str(&format!("{}\t{}\n", entry.timestamp, entry.description));
+        }
+        if !self.undone.is_empty() {
+            history.push_str(&format!("\n{} undone operation(s) can be redone", self.undone.len()));
+        }
+        Ok(history.trim_end_matches("\n").to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::journal::{Journal, JournalEntry};
+    use crate::tracker_data::TrackerData;
+    use crate::date::Date;
+
+    #[test]
+    fn undo_redo() {
+        // Record a few operations
+        let mut tracker_data = TrackerData::new();
+        let mut journal = Journal::new();
+        let date = Date::new_from_string("2023-2-1").unwrap();
+        let before = tracker_data.clone();
+        assert_eq!(tracker_data.add(date.clone(), String::from("guitar"), 30), Ok(()));
+        journal.record(JournalEntry::new("add 30 guitar on 2023-2-1", before.diff(&tracker_data)));
+        let before = tracker_data.clone();
+        assert_eq!(tracker_data.subtract(date.clone(), String::from("guitar"), 30), Ok(()));
+        journal.record(JournalEntry::new("sub 30 guitar on 2023-2-1", before.diff(&tracker_data)));
+        assert_eq!(tracker_data.get(&date, "guitar"), None);
+
+        // Undo the subtraction, which removed the activity
+        let undone = journal.undo(&mut tracker_data, 1).unwrap();
+        assert_eq!(undone[0].description, String::from("sub 30 guitar on 2023-2-1"));
+        assert_eq!(tracker_data.get(&date, "guitar"), Some(30));
+
+        // Undo more operations than there are
+        assert_eq!(journal.undo(&mut tracker_data, 5).unwrap().len(), 1);
+        assert_eq!(tracker_data.get(&date, "guitar"), None);
+        assert!(journal.undo(&mut tracker_data, 1).is_err());
+
+        // Redo the addition, then record something new (which clears the rest of the redo stack)
+        assert_eq!(journal.redo(&mut tracker_data, 1).unwrap().len(), 1);
+        assert_eq!(tracker_data.get(&date, "guitar"), Some(30));
+        let before = tracker_data.clone();
+        assert_eq!(tracker_data.set(date.clone(), String::from("guitar"), 45), Ok(()));
+        journal.record(JournalEntry::new("set guitar to 45 on 2023-2-1", before.diff(&tracker_data)));
+        assert_eq!(journal.redo(&mut tracker_data, 1),
+                    Err(String::from("Redo error: nothing to redo")));
+    }
+
+    #[test]
+    fn from_to_json() {
+        let mut tracker_data = TrackerData::new();
+        let date = Date::new_from_string("2023-2-1").unwrap();
+        let before = tracker_data.clone();
+        assert_eq!(tracker_data.add(date.clone(), String::from("guitar"), 30), Ok(()));
+        let entry = JournalEntry::new("add 30 guitar on 2023-2-1", before.diff(&tracker_data));
+        assert_eq!(JournalEntry::from_json(&entry.to_json()), Ok(entry.clone()));
+        assert_eq!(entry.changes[0].describe(), String::from("2023-2-1 guitar: none -> 30"));
+        assert!(JournalEntry::from_json(&json::object!{description: "missing time"}).is_err());
+    }
+}
@@ -1,2 +1,26 @@
+pub mod activity_registry;
+pub mod charts;
+pub mod compare;
+pub mod csv;
 pub mod date;
-pub mod tracker_data;
\ No newline at end of file
+pub mod encryption;
+pub mod event_log;
+pub mod git_sync;
+pub mod goals;
+pub mod heatmap;
+pub mod html_report;
+pub mod ical;
+pub mod importers;
+pub mod journal;
+pub mod merge;
+pub mod profiles;
+pub mod schema;
+pub mod settings;
+pub mod shell;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_storage;
+pub mod storage;
+pub mod streaks;
+pub mod tracker_data;
+pub mod trend;
+pub mod tui;
\ No newline at end of file
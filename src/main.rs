@@ -1,80 +1,750 @@
+use std::collections::HashMap;
 use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::process;
-use tracker::tracker_data::TrackerData;
+use rustyline::Editor;
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use tracker::tracker_data::{TrackerData, ConflictPolicy};
+use tracker::activity_registry::ActivityRegistry;
+use tracker::journal::{Journal, JournalEntry};
+use tracker::storage::{Storage, open_storage};
+use tracker::settings::Settings;
+use tracker::profiles::Profiles;
+use tracker::goals::{Goals, Goal, Comparison, Period};
+use tracker::streaks;
+use tracker::charts;
+use tracker::compare;
+use tracker::trend;
+use tracker::tui::Tui;
+use tracker::shell::{self, ShellHelper};
+use tracker::heatmap::Heatmap;
+use tracker::html_report;
+use tracker::csv;
+use tracker::ical;
+use tracker::git_sync::GitRepo;
+use tracker::importers;
+use tracker::merge::{MergePolicy, Conflict, DayMinutes, merge_tracker_data, conflict_report};
 use tracker::date::Date;
+use tracker::encryption::{self, Cipher};
+use tracker::schema;
 
-fn main() {
-    // Open the JSON file and load into the tracker data
-    let filename = "tracker_data.json";
-    let mut tracker_data = TrackerData::new();
-    tracker_data.load_from_file(&filename)
-        .unwrap_or_else(|e| print_error_and_exit(&e));
+// An optional restriction to the dates from a start date to an end date
+type DateRange = Option<(Date, Date)>;
+
+// A start date and an end date (such as the two ranges compared by the compare function)
+type Dates = (Date, Date);
+
+// Event names mapped to the activities they should be imported as (such as "Gym" to "exercise")
+type Mappings = Vec<(String, String)>;
+
+// The file, format, conflict policy, dry run and new flags, and event mappings given for import
+type ImportArgs = (String, String, ConflictPolicy, bool, bool, Mappings);
 
-    // Get command line arguments
+// The minimum minutes and the period given for streaks (each optional)
+type StreakOptions = (Option<u32>, Option<Period>);
+
+// The files of every profile (kept in the directory of the profile)
+const SETTINGS_FILENAME: &str = "tracker_settings.json";
+const DATA_FILENAME: &str = "tracker_data.json";
+const LOG_FILENAME: &str = "tracker_events.jsonl";
+const DB_FILENAME: &str = "tracker_data.sqlite3";
+const REGISTRY_FILENAME: &str = "tracker_activities.json";
+const JOURNAL_FILENAME: &str = "tracker_journal.json";
+const GOALS_FILENAME: &str = "tracker_goals.json";
+
+// The file recording the default profile (kept in the current directory)
+const PROFILES_FILENAME: &str = "tracker_profiles.json";
+
+fn main() {
+    // Get command line arguments (the "--profile <name>" option can be given with any function)
     let mut args: Vec<String> = env::args().collect();
     args.remove(0);
-    let mut func_arg = "";
-    let mut other_args = Vec::new();
-    if args.len() > 0 {
-        func_arg = &*args[0];
-        for a in &args[1..] {
-            other_args.push(a.clone());
+    let profile_arg = take_option(&mut args, "--profile", 1).unwrap_or_else(|e| {
+        print_error_and_exit(&e);
+        None
+    });
+
+    // Split the command line arguments into the function and its arguments
+    let func_arg = args.first().cloned().unwrap_or_default();
+    let other_args: Vec<String> = args.into_iter().skip(1).collect();
+
+    // Open the files of the profile to use (the default one if none is given)
+    let mut session = match Session::open(profile_arg.map(|values| values[0].clone()), dates_used(&func_arg, &other_args)) {
+        Ok(s) => s,
+        Err(e) => {
+            print_error_and_exit(&e);
+            return;
+        }
+    };
+
+    // Run the shell, or else run the function and save the changes (unless they were only previewed)
+    if func_arg == "shell" {
+        run_shell(&mut session, other_args)
+            .unwrap_or_else(|e| print_error_and_exit(&e));
+    } else {
+        let save = run_function(&mut session, &func_arg, other_args).unwrap_or_else(|e| {
+            print_error_and_exit(&e);
+            false
+        });
+        if save {
+            session.save()
+                .unwrap_or_else(|e| print_error_and_exit(&e));
         }
     }
+}
+
+// Struct Definition
+// The files of a profile loaded into memory, which are changed by one function (or by many, in the shell) before being
+// saved again
+struct Session {
+    profiles: Profiles,
+    profile_dir: PathBuf,
+    settings: Settings,
+    storage: Box<dyn Storage>,
+    tracker_data: TrackerData,
+    saved: TrackerData,
+    registry: ActivityRegistry,
+    journal: Journal,
+    goals: Goals,
+    descriptions: Vec<String>,
+}
+
+// Public Methods
+impl Session {
+    // Opens the files of a profile (or of the default profile if None)
+    // If the function only uses the dates in dates, storage that applies only the changed entries (SQLite) just loads
+    // those dates, so a single change or summary does not read the whole history
+    fn open(profile: Option<String>, dates: DateRange) -> Result<Session, String> {
+        // Open the profiles file and find the directory of the profile
+        let mut profiles = Profiles::new(Path::new(""));
+        profiles.load_from_file(PROFILES_FILENAME)?;
+        let profile = profile.unwrap_or_else(|| profiles.default_profile());
+        let profile_dir = profiles.dir(&profile)?;
+
+        // Open the settings file and load into the settings
+        let mut settings = Settings::new();
+        settings.load_from_file(&in_dir(&profile_dir, SETTINGS_FILENAME))?;
+
+        // Open the storage for the current storage mode and load the tracker data from it
+        let mut storage = open_storage(&settings.get("storage")?, &in_dir(&profile_dir, DATA_FILENAME),
+                                       &in_dir(&profile_dir, LOG_FILENAME), &in_dir(&profile_dir, DB_FILENAME))?;
+        let tracker_data = match dates {
+            Some((start_date, end_date)) if storage.applies_changes_only() => storage.load_range(&start_date, &end_date)?,
+            _ => storage.load()?,
+        };
+
+        // Open the activities file and load into the activity registry (along with any activities in the tracker data)
+        // The other files of an encrypted profile are encrypted with the same key as the data file, so they are
+        // decrypted with its cipher rather than asking for the passphrase again
+        let mut registry = ActivityRegistry::new();
+        registry.set_cipher(tracker_data.cipher());
+        registry.load_from_file(&in_dir(&profile_dir, REGISTRY_FILENAME))?;
+        registry.register_from(&tracker_data);
+
+        // Open the journal file and load into the journal of past operations
+        let mut journal = Journal::new();
+        journal.set_cipher(tracker_data.cipher());
+        journal.load_from_file(&in_dir(&profile_dir, JOURNAL_FILENAME))?;
+
+        // Open the goals file and load into the goals for activities
+        let mut goals = Goals::new();
+        goals.set_cipher(tracker_data.cipher());
+        goals.load_from_file(&in_dir(&profile_dir, GOALS_FILENAME))?;
+
+        Ok(Session {
+            profiles,
+            profile_dir,
+            settings,
+            storage,
+            saved: tracker_data.clone(),
+            tracker_data,
+            registry,
+            journal,
+            goals,
+            descriptions: Vec::new(),
+        })
+    }
+
+    // Returns the path of one of the files of the profile
+    fn file(self: &Self, name: &str) -> String {
+        in_dir(&self.profile_dir, name)
+    }
+
+    // Save the tracker data, the activity registry, the journal, the goals, and the settings into their files
+    // Only the changes since the last save are handed to the storage, which may save them incrementally
+    fn save(self: &mut Self) -> Result<(), String> {
+        let description = self.descriptions.join("; ");
+        let entry = JournalEntry::new(&description, self.saved.diff(&self.tracker_data));
+        if !entry.changes.is_empty() {
+            self.storage.apply(&self.tracker_data, &entry)?;
+        }
+        self.registry.register_from(&self.tracker_data);
+        self.registry.save_to_file(&self.file(REGISTRY_FILENAME))?;
+        self.journal.save_to_file(&self.file(JOURNAL_FILENAME))?;
+        self.goals.save_to_file(&self.file(GOALS_FILENAME))?;
+        self.settings.save_to_file(&self.file(SETTINGS_FILENAME))?;
+        // Commit the changed data files with the descriptions of the changes as the message
+        if self.settings.get("git").unwrap_or_default() == "on" && !entry.changes.is_empty() {
+            let data_files = [self.file(DATA_FILENAME), self.file(LOG_FILENAME), self.file(DB_FILENAME)];
+            GitRepo::open(Path::new("."))?
+                .autocommit(&data_files.each_ref().map(|f| f.as_str()), &description)?;
+        }
+        self.saved = self.tracker_data.clone();
+        self.descriptions.clear();
+        Ok(())
+    }
+}
 
-    // Run a function depending on the command line arguments
+// Run a function with its arguments on the files of a session, returning whether to keep the changes
+// The changes of functions that only preview them are undone, and so are those of a function that fails part way (such
+// as registering a new activity before its minutes are refused, or changing a setting before its migration is refused),
+// since the shell keeps using the session afterwards
+fn run_function(session: &mut Session, func_arg: &str, other_args: Vec<String>) -> Result<bool, String> {
+    let before = session.tracker_data.clone();
+    let registry_before = session.registry.clone();
+    let settings_before = session.settings.clone();
+    let goals_before = session.goals.clone();
+    let result = apply_function(session, &before, func_arg, other_args);
+    if result != Ok(true) {
+        session.tracker_data = before;
+        session.registry = registry_before;
+        session.settings = settings_before;
+        session.goals = goals_before;
+    }
+    result
+}
+
+// Apply a function to the files of a session (before is the tracker data from before the function), returning false
+// for functions that only preview their changes
+fn apply_function(session: &mut Session, before: &TrackerData, func_arg: &str, mut other_args: Vec<String>)
+                  -> Result<bool, String> {
+    let filename: &str = &session.file(DATA_FILENAME);
+    let log_filename: &str = &session.file(LOG_FILENAME);
+    let db_filename: &str = &session.file(DB_FILENAME);
+    // The files committed when the git setting is on (the activities, journal, goals, and settings stay local to each computer)
+    let data_files = [filename, log_filename, db_filename];
+    let storage_mode = session.settings.get("storage")?;
+    let Session {profiles, profile_dir, settings, storage, tracker_data, saved, registry, journal, goals, descriptions} = session;
+    let mut save = true;
+    let mut description = String::new();
+    let mut record_in_journal = true;
+
+    // Run a function depending on the function argument
     match func_arg {
         // Add time to an activity
         "add" => {
             // Parse the arguments
-            let (date, activity, minutes) = parse_add_sub_args(other_args).unwrap_or_else(|e| {
-                print_error_and_exit(&e);
-                (Date::new(), String::new(), 0)
-            });
+            let allow_new = take_flag(&mut other_args, "--new");
+            let (date, activity, minutes) = parse_add_sub_args(other_args)?;
+            // Make sure the activity is known (or resolve its alias)
+            let activity = registry.check(&activity, allow_new)?;
             // Call the add method on tracker_data
-            tracker_data.add(date, activity, minutes).unwrap_or_else(|e| {
-                print_error_and_exit(&e);
-            });
+            description = format!("add {} {} on {}", minutes, activity, date.to_iso_string());
+            tracker_data.add(date, activity, minutes)?;
         }
         // Remove time from an activity
         "sub" => {
             // Parse the arguments
-            let (date, activity, minutes) = parse_add_sub_args(other_args).unwrap_or_else(|e| {
-                print_error_and_exit(&e);
-                (Date::new(), String::new(), 0)
-            });
+            let (date, activity, minutes) = parse_add_sub_args(other_args)?;
+            let activity = registry.resolve(&activity);
             // Call the subtract method on tracker_data
-            tracker_data.subtract(date, activity, minutes).unwrap_or_else(|e| {
-                print_error_and_exit(&e);
-            });
+            description = format!("sub {} {} on {}", minutes, activity, date.to_iso_string());
+            tracker_data.subtract(date, activity, minutes)?;
+        }
+        // Set the time for an activity
+        "set" => {
+            // Parse the arguments
+            let allow_new = take_flag(&mut other_args, "--new");
+            let (date, activity, minutes) = parse_add_sub_args(other_args)?;
+            // Make sure the activity is known (or resolve its alias)
+            let activity = registry.check(&activity, allow_new)?;
+            // Call the set method on tracker_data
+            description = format!("set {} to {} on {}", activity, minutes, date.to_iso_string());
+            tracker_data.set(date, activity, minutes)?;
+        }
+        // Remove an activity (or all activities) from a date
+        "rm" => {
+            // Parse the arguments
+            let (date, activity) = parse_rm_args(other_args)?;
+            let activity = activity.map(|a| registry.resolve(&a));
+            // Call the remove method on tracker_data
+            description = match &activity {
+                Some(a) => format!("rm {} on {}", a, date.to_iso_string()),
+                None => format!("rm {}", date.to_iso_string()),
+            };
+            tracker_data.remove(date, activity)?;
         }
         // Print a summary of a date range
         "sum" => {
             // Parse the arguments
-            let (start_date, end_date) = parse_sum_args(other_args).unwrap_or_else(|e| {
-                print_error_and_exit(&e);
-                (Date::new(), Date::new())
-            });
-            // Call the summarize method on tracker_data
-            let summary = tracker_data.summarize(start_date, end_date).unwrap_or_else(|e| {
-                print_error_and_exit(&e);
-                String::new()
-            });
+            let all_profiles = take_flag(&mut other_args, "--all-profiles");
+            let of_day = take_flag(&mut other_args, "--of-day");
+            let (start_date, end_date) = parse_sum_args(other_args)?;
+            // Add up the totals of just that date range in storage (or in every profile, added together) and summarize them
+            // Changes made in the shell that are not saved yet are only in memory, so then the totals are taken from there
+            let (totals, num_days) = if all_profiles {
+                println!("Profiles: {}", profiles.names().join(", "));
+                TrackerData::combined_totals(&load_all_profiles_range(profiles, &start_date, &end_date)?, &start_date, &end_date)
+            } else if saved.diff(tracker_data).is_empty() {
+                storage.activity_totals(&start_date, &end_date)?
+            } else {
+                tracker_data.activity_totals(&start_date, &end_date)
+            };
+            let categories: HashMap<String, String> = totals.keys()
+                .filter_map(|activity| registry.category(activity).map(|category| (activity.clone(), category)))
+                .collect();
+            let summary = TrackerData::summarize_totals(start_date, end_date, &totals, num_days, &categories, of_day)?;
             println!("{}", summary);
         }
+        // Compare the totals of every activity in two date ranges
+        "compare" => {
+            let (range_a, range_b) = parse_compare_args(other_args)?;
+            let report = compare::compare(tracker_data, &range_a, &range_b)?;
+            println!("{}", report);
+        }
+        // Print the moving averages, slope, and unusual days of an activity in a date range
+        "trend" => {
+            let (activity, (start_date, end_date)) = parse_trend_args(other_args)?;
+            // Make sure the activity is known (or resolve its alias)
+            let activity = registry.check(&activity, false)?;
+            let report = trend::trend_report(tracker_data, &activity, &start_date, &end_date)?;
+            println!("{}", report);
+        }
+        // Write a report of a date range as a single HTML file
+        "report" => {
+            let (filename, (start_date, end_date)) = parse_report_args(other_args)?;
+            let categories = activity_categories(registry, tracker_data);
+            let html = html_report::html_report(tracker_data, &start_date, &end_date, &categories)?;
+            fs::write(&filename, html).map_err(|_| format!("Save to file error: cannot write to \"{}\"", filename))?;
+            println!("Wrote report from {} to {} to {}", start_date.to_string(), end_date.to_string(), filename);
+        }
+        // Browse and change the tracker data in a full-screen interface, saving the changes on quitting with "q"
+        "tui" => {
+            if !other_args.is_empty() {
+                return Err(String::from("Parse arguments error: \"tui\" function takes no arguments"));
+            }
+            if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+                return Err(String::from("TUI error: \"tui\" function needs to run in a terminal"));
+            }
+            let mut tui = Tui::new(Date::new_from_today());
+            save = tui.run(tracker_data, registry)?;
+            // All the changes are recorded in the journal together, so they can be undone at once
+            if !before.diff(tracker_data).is_empty() {
+                description = String::from("edit in tui");
+            }
+        }
+        // Rename an activity across all dates
+        "rename" => {
+            // Parse the arguments
+            let (old, new, range, preview) = parse_rename_args(other_args)?;
+            // Call the rename_activity method on tracker_data
+            let old = registry.resolve(&old);
+            description = format!("rename {} to {}", old, new);
+            let changes = tracker_data.rename_activity(&old, &new, range)?;
+            println!("{}", changes);
+            // Point the old activity's aliases at the new activity (unless it still has minutes outside the range)
+            replace_activity(registry, tracker_data, &old, &new)?;
+            save = !preview;
+        }
+        // Merge several activities into one across all dates
+        "merge" => {
+            // Parse the arguments
+            let (sources, target, range, preview) = parse_merge_args(other_args)?;
+            // Call the merge_activities method on tracker_data
+            let sources: Vec<String> = sources.iter().map(|s| registry.resolve(s)).collect();
+            description = format!("merge {} into {}", sources.join(", "), target);
+            let changes = tracker_data.merge_activities(&sources, &target, range)?;
+            println!("{}", changes);
+            // Point the merged activities' aliases at the target activity (unless they still have minutes outside the range)
+            for source in &sources {
+                replace_activity(registry, tracker_data, source, &target)?;
+            }
+            save = !preview;
+        }
+        // Print the tracker data in another format
+        "export" => {
+            // Parse the arguments
+            let (format, range) = parse_export_args(other_args)?;
+            // Convert the tracker data to that format
+            match format.as_str() {
+                "csv" => print!("{}", csv::to_csv(tracker_data, range)),
+                "ics" => print!("{}", ical::to_ics(tracker_data, range)),
+                f => return Err(format!("Export error: unknown format \"{}\"", f)),
+            }
+        }
+        // Merge tracker data from a file in another format
+        "import" => {
+            // Parse the arguments
+            let (import_filename, format, policy, dry_run, new, mappings) = parse_import_args(other_args)?;
+            let contents = fs::read_to_string(&import_filename).map_err(|_| format!("Import error: cannot read \"{}\"", import_filename))?;
+            // Convert the contents to rows (intervals from other time trackers are split at midnight) and import them into tracker_data
+            let (rows, skipped) = match format.as_str() {
+                "csv" => csv::from_csv(&contents).map(|rows| (rows, Vec::new())),
+                "timewarrior" | "data" => Ok(importers::from_timewarrior(&contents)),
+                "toggl" => importers::from_toggl_csv(&contents),
+                "org" => Ok(importers::from_org_clock(&contents)),
+                "ics" => Ok(ical::from_ics(&contents, &mappings)),
+                f => Err(format!("Import error: unknown format \"{}\"", f)),
+            }?;
+            // Every activity has to be known (or be an alias for one), unless "--new" is given to create the rest
+            let rows = rows.into_iter()
+                .map(|(date, activity, minutes)| registry.check(&activity, new).map(|a| (date, a, minutes)))
+                .collect::<Result<Vec<_>, String>>()?;
+            tracker_data.import(&rows, policy)?;
+            // Show what changed (or what would change, for a dry run)
+            let changes = before.diff(tracker_data);
+            if dry_run {
+                println!("Importing {} row(s) from {} would make {} change(s):", rows.len(), import_filename, changes.len());
+            } else {
+                println!("Imported {} row(s) from {}, making {} change(s):", rows.len(), import_filename, changes.len());
+            }
+            for change in &changes {
+                println!("{}", change.describe());
+            }
+            if !skipped.is_empty() {
+                println!("\nSkipped {} line(s):", skipped.len());
+                for line in &skipped {
+                    println!("{}", line);
+                }
+            }
+            description = format!("import {}", import_filename);
+            save = !dry_run;
+        }
+        // Combine another tracker data file (such as one from another computer) into the tracker data
+        "merge-file" => {
+            // Parse the arguments
+            let (other_filename, policy, dry_run) = parse_merge_file_args(other_args)?;
+            if fs::metadata(&other_filename).is_err() {
+                return Err(format!("Merge file error: cannot find \"{}\"", other_filename));
+            }
+            // A file encrypted with another key than the data file asks for its own passphrase
+            let mut other_data = TrackerData::new();
+            other_data.set_cipher(tracker_data.cipher());
+            other_data.load_from_file(&other_filename)?;
+            // Merge the other tracker data into tracker_data and report the conflicts
+            let resolved = merge_tracker_data(tracker_data, &other_data, policy, &mut ask_about_conflict)?;
+            let changes = before.diff(tracker_data);
+            if dry_run {
+                println!("Merging {} would make {} change(s)", other_filename, changes.len());
+            } else {
+                println!("Merged {}, making {} change(s)", other_filename, changes.len());
+            }
+            println!("{}", conflict_report(&resolved));
+            description = format!("merge-file {}", other_filename);
+            save = !dry_run;
+        }
+        // Pull from a git remote, merge the tracker data, and push the result
+        "sync" => {
+            // Parse the arguments
+            let (remote, policy) = parse_sync_args(other_args)?;
+            if storage_mode != "json" {
+                return Err(String::from("Sync error: sync only works with json storage (use \"migrate --to json\")"));
+            }
+            let repo = GitRepo::open(Path::new("."))?;
+            // Commit any local changes first (writing any not saved yet in the shell), then sync and reload the merged
+            // tracker data
+            if !saved.diff(tracker_data).is_empty() {
+                storage.save(tracker_data)?;
+            }
+            repo.autocommit(&data_files, "sync: commit local changes")?;
+            let report = repo.sync(filename, &tracker_data.cipher(), &remote, policy, &mut ask_about_conflict)?;
+            println!("{}", report);
+            *tracker_data = storage.load()?;
+            description = format!("sync {}", remote);
+        }
+        // Revert the last operations
+        "undo" => {
+            let count = parse_count_args(other_args)?;
+            let undone = journal.undo(tracker_data, count)?;
+            let descriptions: Vec<String> = undone.into_iter().map(|e| e.description).collect();
+            for d in &descriptions {
+                println!("Undid: {}", d);
+            }
+            description = format!("undo {}", descriptions.join("; "));
+            record_in_journal = false;
+        }
+        // Reapply the last undone operations
+        "redo" => {
+            let count = parse_count_args(other_args)?;
+            let redone = journal.redo(tracker_data, count)?;
+            let descriptions: Vec<String> = redone.into_iter().map(|e| e.description).collect();
+            for d in &descriptions {
+                println!("Redid: {}", d);
+            }
+            description = format!("redo {}", descriptions.join("; "));
+            record_in_journal = false;
+        }
+        // Print the recent operations
+        "history" => {
+            let mut count = 10;
+            if !other_args.is_empty() {
+                count = parse_count_args(other_args)?;
+            }
+            let history = journal.history(count)?;
+            println!("{}", history);
+        }
+        // Encrypt the data file (along with the activities, journal, and goals files) with a new passphrase
+        "encrypt" => {
+            if storage_mode != "json" {
+                return Err(String::from("Encrypt error: encryption only works with json storage (use \"migrate --to json\")"));
+            }
+            if tracker_data.is_encrypted() {
+                return Err(format!("Encrypt error: \"{}\" is already encrypted", filename));
+            }
+            let cipher = encryption::new_passphrase().and_then(|p| Cipher::new(&p))?;
+            tracker_data.set_cipher(Some(cipher.clone()));
+            registry.set_cipher(Some(cipher.clone()));
+            journal.set_cipher(Some(cipher.clone()));
+            goals.set_cipher(Some(cipher));
+            storage.save(tracker_data)?;
+            // Backups of older format versions are plaintext copies of the data file
+            for backup_filename in schema::remove_backups(filename)? {
+                println!("Removed unencrypted backup {}", backup_filename);
+            }
+            println!("Encrypted {} (set {} to avoid being asked for the passphrase)", filename, encryption::PASSPHRASE_VAR);
+        }
+        // Decrypt the data file (along with the activities, journal, and goals files)
+        "decrypt" => {
+            if !tracker_data.is_encrypted() {
+                return Err(format!("Decrypt error: \"{}\" is not encrypted", filename));
+            }
+            tracker_data.set_cipher(None);
+            registry.set_cipher(None);
+            journal.set_cipher(None);
+            goals.set_cipher(None);
+            storage.save(tracker_data)?;
+            println!("Decrypted {}", filename);
+        }
+        // Fold the event log back into the JSON file
+        "compact" => {
+            storage.save(tracker_data)?;
+            println!("Compacted the tracker data into {}", filename);
+        }
+        // Print or change the settings
+        "config" => {
+            match other_args.len() {
+                0 => println!("{}", settings.describe()),
+                1 => {
+                    let value = settings.get(&other_args[0])?;
+                    println!("{}", value);
+                }
+                2 => {
+                    settings.set(&other_args[0], &other_args[1])?;
+                    // Changing the storage mode moves the tracker data into the new storage
+                    if other_args[0] == "storage" && other_args[1] != storage_mode {
+                        *storage = migrate_storage(storage.as_mut(), tracker_data, &other_args[1], filename, log_filename, db_filename)?;
+                    }
+                }
+                _ => return Err(String::from("Parse arguments error: too many arguments for \"config\" function")),
+            }
+        }
+        // Move the tracker data to another storage mode
+        "migrate" => {
+            let mode: String;
+            match take_option(&mut other_args, "--to", 1) {
+                Ok(Some(values)) if other_args.is_empty() => mode = values[0].clone(),
+                _ => return Err(String::from("Parse arguments error: \"migrate\" function needs a storage mode (use \"--to <mode>\")")),
+            }
+            if mode == storage_mode {
+                return Err(format!("Migrate error: already using {} storage", mode));
+            }
+            *storage = migrate_storage(storage.as_mut(), tracker_data, &mode, filename, log_filename, db_filename)?;
+            settings.set("storage", &mode)?;
+            println!("Migrated {} entries from {} storage to {} storage", tracker_data.entries().len(), storage_mode, mode);
+        }
+        // List the known activities
+        "activities" => {
+            let list = registry.describe(tracker_data)?;
+            println!("{}", list);
+        }
+        // Add an alias for an activity
+        "alias" => {
+            if other_args.len() != 2 {
+                return Err(String::from("Parse arguments error: \"alias\" function needs exactly one alias and one activity"));
+            }
+            registry.add_alias(&other_args[0], &other_args[1])?;
+        }
+        // Remove an alias
+        "unalias" => {
+            if other_args.len() != 1 {
+                return Err(String::from("Parse arguments error: \"unalias\" function needs exactly one alias"));
+            }
+            registry.remove_alias(&other_args[0])?;
+        }
+        // Tag an activity with a category
+        "tag" => {
+            if other_args.len() != 2 {
+                return Err(String::from("Parse arguments error: \"tag\" function needs exactly one activity and one category"));
+            }
+            let activity = registry.check(&other_args[0], false)?;
+            registry.tag(&activity, &other_args[1])?;
+        }
+        // Remove the category an activity is tagged with
+        "untag" => {
+            if other_args.len() != 1 {
+                return Err(String::from("Parse arguments error: \"untag\" function needs exactly one activity"));
+            }
+            let activity = registry.resolve(&other_args[0]);
+            registry.untag(&activity)?;
+        }
+        // Add or remove a goal for an activity
+        "goal" => {
+            match other_args.first().map(|a| a.as_str()) {
+                Some("set") => {
+                    let goal = parse_goal_args(&other_args[1..])?;
+                    // Make sure the activity is known (or resolve its alias)
+                    let activity = registry.check(&goal.activity, false)?;
+                    println!("Set goal {} {}", activity, goal.describe());
+                    goals.set(Goal {activity, ..goal});
+                }
+                Some("rm") if other_args.len() == 2 || other_args.len() == 3 => {
+                    let period = other_args.get(2).map(|p| Period::new_from_string(p)).transpose()?;
+                    goals.remove(&registry.resolve(&other_args[1]), period)?;
+                }
+                _ => return Err(String::from("Parse arguments error: \"goal\" function needs set <activity> at-least|at-most <minutes> day|week|month, or rm <activity> [<period>]")),
+            }
+        }
+        // Print the progress towards every goal, and whether they were hit in past periods
+        "goals" => {
+            let history = parse_goals_args(other_args)?;
+            let report = goals.report(tracker_data, &Date::new_from_today(), history)?;
+            println!("{}", report);
+        }
+        // Print a bar chart of the minutes of an activity (or every activity) in each day, week, or month of a date range
+        "chart" => {
+            let (activity, start_date, end_date, period, width) = parse_chart_args(other_args)?;
+            // Chart every activity with time in the range for "all", or else make sure the activity is known
+            let activities: Vec<String> = if activity == "all" {
+                let mut all: Vec<String> = tracker_data.range(&start_date, &end_date).activity_usage().into_keys().collect();
+                all.sort();
+                all
+            } else {
+                vec![registry.check(&activity, false)?]
+            };
+            let chart = charts::bar_chart(tracker_data, &activities, &start_date, &end_date, period, width)?;
+            println!("{}", chart);
+        }
+        // Print a calendar heatmap of the minutes of an activity on every day of a year
+        "heatmap" => {
+            // Colors are only used on a terminal, and never when NO_COLOR is set (see no-color.org)
+            let no_color = take_flag(&mut other_args, "--no-color");
+            let color = !no_color && io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none();
+            let (activity, year) = parse_heatmap_args(other_args)?;
+            // Make sure the activity is known (or resolve its alias)
+            let activity = registry.check(&activity, false)?;
+            match Heatmap::new(tracker_data, &activity, year) {
+                Ok(heatmap) => println!("{}", heatmap.render(color)),
+                Err(e) => return Err(e),
+            }
+        }
+        // Print the current and longest streaks of periods with time for activities
+        "streaks" => {
+            let (activity, options) = parse_streaks_args(other_args)?;
+            // Make sure the activity is known (or resolve its alias)
+            let activity = activity.map(|a| registry.check(&a, false)).transpose()?;
+            let targets = streak_targets(tracker_data, goals, activity, options);
+            let report = streaks::streaks_report(tracker_data, &targets, &Date::new_from_today())?;
+            println!("{}", report);
+        }
+        // List, create, or delete profiles, or print or change the default profile
+        "profile" => {
+            // Only the profiles file changes, so the files of the profile in use are not saved
+            // The profile in use cannot be deleted, since its files would be written again when the shell saves them
+            save = false;
+            let force = take_flag(&mut other_args, "--force");
+            let result = match (other_args.first().map(|a| a.as_str()), other_args.len()) {
+                (Some("list"), 1) => Ok(profiles.describe()),
+                (Some("create"), 2) => profiles.create(&other_args[1])
+                    .map(|_| format!("Created profile {} (use it with \"--profile {}\")", other_args[1], other_args[1])),
+                (Some("delete"), 2) if profiles.dir(&other_args[1]).ok().as_ref() == Some(profile_dir) => {
+                    Err(format!("Profile error: \"{}\" is the profile in use (delete it from another profile)", other_args[1]))
+                }
+                (Some("delete"), 2) => profiles.delete(&other_args[1], force)
+                    .map(|_| format!("Deleted profile {}", other_args[1])),
+                (Some("default"), 1) => Ok(profiles.default_profile()),
+                (Some("default"), 2) => profiles.set_default(&other_args[1])
+                    .and_then(|_| profiles.save_to_file(PROFILES_FILENAME))
+                    .map(|_| format!("{} is now the default profile", other_args[1])),
+                _ => Err(String::from("Parse arguments error: \"profile\" function needs list, create <name>, delete <name>, or default [<name>]")),
+            };
+            match result {
+                Ok(message) => println!("{}", message),
+                Err(e) => return Err(e),
+            }
+        }
         // Asking for help, print out instructions
         "help" => {
             print_instructions();
         }
         // Invalid function argument, print instructions
         c => {
-            print_error_and_exit(&format!("Parse arguments error: \"{c}\" not a valid function (use \"help\" to see a list of valid functions)"))
+            return Err(format!("Parse arguments error: \"{c}\" not a valid function (use \"help\" to see a list of valid functions)"))
+        }
+    }
+
+    if !save {
+        return Ok(false);
+    }
+    // Record the changes in the journal, and keep their description for the next save
+    if !description.is_empty() {
+        if record_in_journal {
+            journal.record(JournalEntry::new(&description, before.diff(tracker_data)));
         }
+        descriptions.push(description);
     }
+    Ok(true)
+}
 
-    // Save the tracker log into the JSON file
-    tracker_data.save_to_file(&filename)
-        .unwrap_or_else(|e| print_error_and_exit(&e));
+// Run functions entered line by line on the files of a session (with history and tab completion) until "exit" is
+// entered, saving the changes then (or after every function, with "--autosave")
+// An error only stops the function it came from, so the shell keeps running
+fn run_shell(session: &mut Session, mut other_args: Vec<String>) -> Result<(), String> {
+    let autosave = take_flag(&mut other_args, "--autosave");
+    if !other_args.is_empty() {
+        return Err(String::from("Parse arguments error: too many arguments for \"shell\" function"));
+    }
+    let mut editor: Editor<ShellHelper, DefaultHistory> = Editor::new().map_err(|e| format!("Shell error: {}", e))?;
+    editor.set_helper(Some(ShellHelper::new(session.registry.names())));
+    if io::stdin().is_terminal() {
+        println!("Enter functions as with tracker (such as \"add today guitar 30\"), \"help\" to list them, or \"exit\" to save and quit");
+    }
+    loop {
+        let line = match editor.readline("tracker> ") {
+            Ok(line) => line,
+            // Ctrl-C only clears the line, while Ctrl-D exits like "exit"
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(format!("Shell error: {}", e)),
+        };
+        let words = match shell::split_line(&line) {
+            Ok(words) if words.is_empty() => continue,
+            Ok(words) => words,
+            Err(e) => {
+                eprintln!("{}", e);
+                continue;
+            }
+        };
+        editor.add_history_entry(line.as_str()).ok();
+        match words[0].as_str() {
+            "exit" | "quit" => break,
+            "shell" => eprintln!("Shell error: already in the shell"),
+            func_arg => {
+                let result = run_function(session, func_arg, words[1..].to_vec())
+                    .and_then(|save| if save && autosave {session.save()} else {Ok(())});
+                if let Err(e) = result {
+                    eprintln!("{}", e);
+                }
+            }
+        }
+        // Complete any activities or aliases added by the function
+        if let Some(helper) = editor.helper_mut() {
+            helper.set_names(session.registry.names());
+        }
+    }
+    session.save()
 }
 
 // Parse arguments into values needed for add and sub functions
@@ -98,8 +768,11 @@ fn parse_add_sub_args(other_args: Vec<String>) -> Result<(Date, String, u16), St
     Ok((date, activity, minutes))
 }
 
-// Parse arguments into values needed for sum function
+// Parse arguments into values needed for sum function (a date, two dates, or a date range keyword such as this-week)
 fn parse_sum_args(other_args: Vec<String>) -> Result<(Date, Date), String> {
+    if other_args.len() == 1 && shell::RANGE_KEYWORDS.contains(&other_args[0].as_str()) {
+        return parse_range_arg(&other_args, &mut 0);
+    }
     let start_date: Date;
     let end_date: Date;
     match other_args.len() {
@@ -127,6 +800,422 @@ fn parse_sum_args(other_args: Vec<String>) -> Result<(Date, Date), String> {
     Ok((start_date, end_date))
 }
 
+// Point the aliases and tag of an activity at the activity it was renamed or merged into
+// If the old activity still has minutes (such as outside the range of the rename), it keeps its aliases and tag, and
+// the new activity is only registered
+fn replace_activity(registry: &mut ActivityRegistry, tracker_data: &TrackerData, old: &str, new: &str) -> Result<(), String> {
+    if tracker_data.activity_usage().contains_key(old) {
+        registry.register(new)
+    } else {
+        registry.replace(old, new);
+        Ok(())
+    }
+}
+
+// Returns the only dates a function reads or changes, for the functions that work on a single date or date range
+// (None for the others, or if the dates cannot be found, which leaves the errors to the function itself)
+fn dates_used(func_arg: &str, other_args: &[String]) -> DateRange {
+    let args: Vec<String> = other_args.iter().filter(|a| !a.starts_with("--")).cloned().collect();
+    match func_arg {
+        "add" | "sub" | "set" | "rm" => {
+            let date = Date::new_from_string(args.first()?).ok()?;
+            Some((date.clone(), date))
+        }
+        "sum" => parse_sum_args(args).ok(),
+        _ => None,
+    }
+}
+
+// Move the tracker data from the current storage into the storage for another storage mode
+// The current storage is saved first (folding any event log), and the new storage must load back exactly the same data
+// (compared as it would be written to the data file, so the metadata has to come back too)
+fn migrate_storage(storage: &mut dyn Storage, tracker_data: &TrackerData, mode: &str, filename: &str, log_filename: &str, db_filename: &str) -> Result<Box<dyn Storage>, String> {
+    // Only the JSON data file can be encrypted, so the other modes would store the tracker data unencrypted
+    if tracker_data.is_encrypted() && mode != "json" {
+        return Err(String::from("Migrate error: the data file is encrypted (use \"decrypt\" first)"));
+    }
+    storage.save(tracker_data)?;
+    let mut new_storage = open_storage(mode, filename, log_filename, db_filename)?;
+    new_storage.save(tracker_data)?;
+    if new_storage.load()?.save_to_string()? != tracker_data.save_to_string()? {
+        return Err(format!("Migrate error: tracker data did not round trip through {} storage", mode));
+    }
+    Ok(new_storage)
+}
+
+// Parse arguments into a goal for the goal set function
+fn parse_goal_args(other_args: &[String]) -> Result<Goal, String> {
+    if other_args.len() != 4 {
+        return Err(String::from("Parse arguments error: \"goal set\" function needs <activity> at-least|at-most <minutes> day|week|month"));
+    }
+    let minutes = other_args[2].parse::<u32>()
+        .map_err(|_| format!("Parse arguments error: \"{}\" cannot be interpreted as an integer", other_args[2]))?;
+    Ok(Goal {
+        activity: other_args[0].clone(),
+        comparison: Comparison::new_from_string(&other_args[1])?,
+        minutes,
+        period: Period::new_from_string(&other_args[3])?,
+    })
+}
+
+// Parse arguments into the number of past periods for the goals function (default 4)
+fn parse_goals_args(mut other_args: Vec<String>) -> Result<u16, String> {
+    let history = match take_option(&mut other_args, "--history", 1)? {
+        Some(values) => values[0].parse::<u16>()
+            .map_err(|_| format!("Parse arguments error: \"{}\" cannot be interpreted as an integer", values[0]))?,
+        None => 4,
+    };
+    if !other_args.is_empty() {
+        return Err(String::from("Parse arguments error: too many arguments for \"goals\" function"));
+    }
+    Ok(history)
+}
+
+// Parse arguments into the activity, date range, period, and width for the chart function
+// The width defaults to the width of the terminal, or else 80 columns (such as when the output is piped)
+fn parse_chart_args(mut other_args: Vec<String>) -> Result<(String, Date, Date, Period, usize), String> {
+    let period = match take_option(&mut other_args, "--by", 1)? {
+        Some(values) => Period::new_from_string(&values[0])?,
+        None => Period::Day,
+    };
+    let width = match take_option(&mut other_args, "--width", 1)? {
+        Some(values) => values[0].parse::<usize>()
+            .map_err(|_| format!("Parse arguments error: \"{}\" cannot be interpreted as an integer", values[0]))?,
+        None => crossterm::terminal::size().map(|(columns, _)| columns as usize).unwrap_or(80),
+    };
+    if other_args.len() != 3 {
+        return Err(String::from("Parse arguments error: \"chart\" function needs an activity (or all), a start date, and an end date"));
+    }
+    Ok((other_args[0].clone(), parse_date(&other_args[1])?, parse_date(&other_args[2])?, period, width))
+}
+
+// Parse arguments into the activity and year (default this year) for the heatmap function
+fn parse_heatmap_args(mut other_args: Vec<String>) -> Result<(String, u16), String> {
+    let year = match take_option(&mut other_args, "--year", 1)? {
+        Some(values) => values[0].parse::<u16>()
+            .map_err(|_| format!("Parse arguments error: \"{}\" cannot be interpreted as a year", values[0]))?,
+        None => Date::new_from_today().to_tuple().0,
+    };
+    if other_args.len() != 1 {
+        return Err(String::from("Parse arguments error: \"heatmap\" function needs exactly one activity"));
+    }
+    Ok((other_args[0].clone(), year))
+}
+
+// Parse arguments into the activity, minimum minutes, and period for the streaks function
+fn parse_streaks_args(mut other_args: Vec<String>) -> Result<(Option<String>, StreakOptions), String> {
+    let minimum = match take_option(&mut other_args, "--min", 1)? {
+        Some(values) => Some(values[0].parse::<u32>()
+            .map_err(|_| format!("Parse arguments error: \"{}\" cannot be interpreted as an integer", values[0]))?),
+        None => None,
+    };
+    let period = match take_option(&mut other_args, "--period", 1)? {
+        Some(values) => Some(Period::new_from_string(&values[0])?),
+        None => None,
+    };
+    match other_args.len() {
+        0 => Ok((None, (minimum, period))),
+        1 => Ok((Some(other_args[0].clone()), (minimum, period))),
+        _ => Err(String::from("Parse arguments error: too many arguments for \"streaks\" function")),
+    }
+}
+
+// Returns the (activity, minimum, period) targets to find streaks for, for the activity or else every activity
+// A given minimum or period is used for every activity, and otherwise each at-least goal of an activity is used
+// (or any time at all on each day, for activities without one)
+fn streak_targets(tracker_data: &TrackerData, goals: &Goals, activity: Option<String>, options: StreakOptions)
+                  -> Vec<(String, u32, Period)> {
+    let (minimum, period) = options;
+    let activities: Vec<String> = match activity {
+        Some(a) => vec![a],
+        None => {
+            let mut all: Vec<String> = tracker_data.activity_usage().into_keys().collect();
+            all.sort();
+            all
+        }
+    };
+    let mut targets: Vec<(String, u32, Period)> = Vec::new();
+    for a in activities {
+        let goal_targets: Vec<(String, u32, Period)> = goals.list().iter()
+            .filter(|g| g.activity == a && g.comparison == Comparison::AtLeast && g.minutes > 0)
+            .map(|g| (a.clone(), g.minutes, g.period))
+            .collect();
+        if minimum.is_some() || period.is_some() || goal_targets.is_empty() {
+            targets.push((a, minimum.unwrap_or(1), period.unwrap_or(Period::Day)));
+        } else {
+            targets.extend(goal_targets);
+        }
+    }
+    targets
+}
+
+// Load the tracker data from a start date to an end date from the storage of every profile
+fn load_all_profiles_range(profiles: &Profiles, start_date: &Date, end_date: &Date) -> Result<Vec<TrackerData>, String> {
+    let mut range_datas = Vec::new();
+    for name in profiles.names() {
+        let dir = profiles.dir(&name)?;
+        let mut settings = Settings::new();
+        settings.load_from_file(&in_dir(&dir, SETTINGS_FILENAME))?;
+        let mut storage = open_storage(&settings.get("storage")?, &in_dir(&dir, DATA_FILENAME),
+                                   &in_dir(&dir, LOG_FILENAME), &in_dir(&dir, DB_FILENAME))?;
+        range_datas.push(storage.load_range(start_date, end_date)?);
+    }
+    Ok(range_datas)
+}
+
+// Parse arguments into values needed for export function
+fn parse_export_args(mut other_args: Vec<String>) -> Result<(String, DateRange), String> {
+    let format = match take_option(&mut other_args, "--format", 1)? {
+        Some(values) => values[0].clone(),
+        None => String::from("csv"),
+    };
+    let range = match other_args.len() {
+        0 => None,
+        1 => Some((parse_date(&other_args[0])?, parse_date(&other_args[0])?)),
+        2 => Some((parse_date(&other_args[0])?, parse_date(&other_args[1])?)),
+        _ => return Err(String::from("Parse arguments error: too many arguments for \"export\" function")),
+    };
+    Ok((format, range))
+}
+
+// Parse arguments into values needed for import function (the format defaults to the file's extension)
+fn parse_import_args(mut other_args: Vec<String>) -> Result<ImportArgs, String> {
+    let dry_run = take_flag(&mut other_args, "--dry-run");
+    let new = take_flag(&mut other_args, "--new");
+    let mut mappings: Mappings = Vec::new();
+    while let Some(values) = take_option(&mut other_args, "--map", 1)? {
+        match values[0].split_once('=') {
+            Some((from, to)) if !from.trim().is_empty() && !to.trim().is_empty() => {
+                mappings.push((from.trim().to_string(), to.trim().to_string()));
+            }
+            _ => return Err(format!("Parse arguments error: \"{}\" cannot be interpreted as a mapping (use \"<event>=<activity>\")", values[0])),
+        }
+    }
+    let policy = match take_option(&mut other_args, "--policy", 1)? {
+        Some(values) => ConflictPolicy::new_from_string(&values[0])?,
+        None => ConflictPolicy::Add,
+    };
+    let format = take_option(&mut other_args, "--format", 1)?.map(|values| values[0].clone());
+    if other_args.len() != 1 {
+        return Err(String::from("Parse arguments error: \"import\" function needs exactly one file"));
+    }
+    let import_filename = other_args[0].clone();
+    let format = match format {
+        Some(f) => f,
+        None => import_filename.rsplit('.').next().unwrap_or("").to_lowercase(),
+    };
+    if !mappings.is_empty() && format != "ics" {
+        return Err(String::from("Parse arguments error: \"--map\" option only applies to ics imports"));
+    }
+    Ok((import_filename, format, policy, dry_run, new, mappings))
+}
+
+// Parse arguments into values needed for merge-file function
+fn parse_merge_file_args(mut other_args: Vec<String>) -> Result<(String, MergePolicy, bool), String> {
+    let dry_run = take_flag(&mut other_args, "--dry-run");
+    let policy = match take_option(&mut other_args, "--policy", 1)? {
+        Some(values) => MergePolicy::new_from_string(&values[0])?,
+        None => MergePolicy::Max,
+    };
+    if other_args.len() != 1 {
+        return Err(String::from("Parse arguments error: \"merge-file\" function needs exactly one file"));
+    }
+    Ok((other_args[0].clone(), policy, dry_run))
+}
+
+// Parse arguments into values needed for sync function (the remote defaults to "origin")
+fn parse_sync_args(mut other_args: Vec<String>) -> Result<(String, MergePolicy), String> {
+    let policy = match take_option(&mut other_args, "--policy", 1)? {
+        Some(values) => MergePolicy::new_from_string(&values[0])?,
+        None => MergePolicy::Max,
+    };
+    match other_args.len() {
+        0 => Ok((String::from("origin"), policy)),
+        1 => Ok((other_args[0].clone(), policy)),
+        _ => Err(String::from("Parse arguments error: too many arguments for \"sync\" function")),
+    }
+}
+
+// Parse arguments into values needed for rm function
+fn parse_rm_args(other_args: Vec<String>) -> Result<(Date, Option<String>), String> {
+    match other_args.len() {
+        1 => Ok((parse_date(&other_args[0])?, None)),
+        2 => Ok((parse_date(&other_args[0])?, Some(other_args[1].clone()))),
+        0 => Err(String::from("Parse arguments error: not enough arguments for \"rm\" function")),
+        _ => Err(String::from("Parse arguments error: too many arguments for \"rm\" function")),
+    }
+}
+
+// Parse arguments into the optional count needed for undo, redo, and history functions (defaults to 1)
+fn parse_count_args(other_args: Vec<String>) -> Result<usize, String> {
+    match other_args.len() {
+        0 => Ok(1),
+        1 => match other_args[0].parse::<usize>() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(format!("Parse arguments error: \"{}\" cannot be interpreted as a positive integer", other_args[0])),
+        },
+        _ => Err(String::from("Parse arguments error: too many arguments")),
+    }
+}
+
+// Parse arguments into the two date ranges for the compare function
+fn parse_compare_args(other_args: Vec<String>) -> Result<(Dates, Dates), String> {
+    let mut i: usize = 0;
+    let range_a = parse_range_arg(&other_args, &mut i)?;
+    let range_b = parse_range_arg(&other_args, &mut i)?;
+    if i != other_args.len() {
+        return Err(String::from("Parse arguments error: too many arguments for \"compare\" function"));
+    }
+    Ok((range_a, range_b))
+}
+
+// Parse arguments into the activity and date range for the trend function
+fn parse_trend_args(other_args: Vec<String>) -> Result<(String, Dates), String> {
+    let activity = match other_args.first() {
+        Some(a) => a.clone(),
+        None => return Err(String::from("Parse arguments error: \"trend\" function needs an activity and a date range")),
+    };
+    let mut i: usize = 1;
+    let range = parse_range_arg(&other_args, &mut i)?;
+    if i != other_args.len() {
+        return Err(String::from("Parse arguments error: too many arguments for \"trend\" function"));
+    }
+    Ok((activity, range))
+}
+
+// Parse arguments into the output file and date range for the report function
+fn parse_report_args(mut other_args: Vec<String>) -> Result<(String, Dates), String> {
+    let filename = match take_option(&mut other_args, "--html", 1)? {
+        Some(values) => values[0].clone(),
+        None => return Err(String::from("Parse arguments error: \"report\" function needs \"--html <file>\"")),
+    };
+    let mut i: usize = 0;
+    let range = parse_range_arg(&other_args, &mut i)?;
+    if i != other_args.len() {
+        return Err(String::from("Parse arguments error: too many arguments for \"report\" function"));
+    }
+    Ok((filename, range))
+}
+
+// Parse the date range starting at argument i (moving i past it), which is either a start date and an end date, or
+// one of this-week, last-week, this-month, and last-month
+fn parse_range_arg(other_args: &[String], i: &mut usize) -> Result<Dates, String> {
+    let period = match other_args.get(*i).map(|a| a.as_str()) {
+        Some("this-week") | Some("last-week") => Some(Period::Week),
+        Some("this-month") | Some("last-month") => Some(Period::Month),
+        Some(_) => None,
+        None => return Err(String::from("Parse arguments error: not enough arguments (expected a date range)")),
+    };
+    if let Some(p) = period {
+        let mut range = p.bounds(&Date::new_from_today())?;
+        if other_args[*i].starts_with("last-") {
+            range = p.bounds(&range.0.sub_days(1)?)?;
+        }
+        *i += 1;
+        return Ok(range);
+    }
+    if *i + 1 >= other_args.len() {
+        return Err(String::from("Parse arguments error: not enough arguments (expected a start date and an end date)"));
+    }
+    let range = (parse_date(&other_args[*i])?, parse_date(&other_args[*i + 1])?);
+    *i += 2;
+    Ok(range)
+}
+
+// Parse arguments into values needed for rename function
+fn parse_rename_args(mut other_args: Vec<String>) -> Result<(String, String, DateRange, bool), String> {
+    let preview = take_flag(&mut other_args, "--preview");
+    let range = take_range(&mut other_args)?;
+    if other_args.len() != 2 {
+        return Err(String::from("Parse arguments error: \"rename\" function needs exactly one old and one new activity"));
+    }
+    Ok((other_args[0].clone(), other_args[1].clone(), range, preview))
+}
+
+// Parse arguments into values needed for merge function
+fn parse_merge_args(mut other_args: Vec<String>) -> Result<(Vec<String>, String, DateRange, bool), String> {
+    let preview = take_flag(&mut other_args, "--preview");
+    let range = take_range(&mut other_args)?;
+    let target: String;
+    match take_option(&mut other_args, "--into", 1)? {
+        Some(values) => target = values[0].clone(),
+        None => return Err(String::from("Parse arguments error: \"merge\" function needs a target activity (use \"--into <activity>\")")),
+    }
+    if other_args.is_empty() {
+        return Err(String::from("Parse arguments error: not enough arguments for \"merge\" function"));
+    }
+    Ok((other_args, target, range, preview))
+}
+
+// Parse a single argument into a date
+fn parse_date(date_arg: &str) -> Result<Date, String> {
+    match Date::new_from_string(date_arg) {
+        Ok(d) => Ok(d),
+        Err(_) => Err(format!("Parse arguments error: \"{}\" cannot be interpreted as a date", date_arg)),
+    }
+}
+
+// Returns the path of a file in a directory (just the file name for the current directory)
+fn in_dir(dir: &Path, file: &str) -> String {
+    dir.join(file).to_string_lossy().to_string()
+}
+
+// Remove a flag (such as "--preview") from the arguments, returning whether it was present
+fn take_flag(other_args: &mut Vec<String>, flag: &str) -> bool {
+    match other_args.iter().position(|a| a == flag) {
+        Some(i) => {
+            other_args.remove(i);
+            true
+        }
+        None => false,
+    }
+}
+
+// Remove an option (such as "--into <activity>") and the given number of values following it from the arguments
+fn take_option(other_args: &mut Vec<String>, option: &str, num_values: usize) -> Result<Option<Vec<String>>, String> {
+    match other_args.iter().position(|a| a == option) {
+        Some(i) => {
+            if i + num_values >= other_args.len() {
+                return Err(format!("Parse arguments error: not enough values for \"{}\" option", option));
+            }
+            let values: Vec<String> = other_args.drain(i..=i + num_values).skip(1).collect();
+            Ok(Some(values))
+        }
+        None => Ok(None),
+    }
+}
+
+// Returns the categories (from their tags or hierarchical names) of the activities with time in the tracker data
+fn activity_categories(registry: &ActivityRegistry, tracker_data: &TrackerData) -> HashMap<String, String> {
+    tracker_data.activity_usage().into_keys()
+        .filter_map(|activity| registry.category(&activity).map(|category| (activity, category)))
+        .collect()
+}
+
+// Remove a "--range <start_date> <end_date>" option from the arguments and parse it into dates
+fn take_range(other_args: &mut Vec<String>) -> Result<DateRange, String> {
+    match take_option(other_args, "--range", 2)? {
+        Some(values) => Ok(Some((parse_date(&values[0])?, parse_date(&values[1])?))),
+        None => Ok(None),
+    }
+}
+
+// Ask on standard input how to resolve a conflict, repeating the question until the answer can be understood
+fn ask_about_conflict(conflict: &Conflict) -> Result<DayMinutes, String> {
+    loop {
+        print!("{} (keep [o]urs, [t]heirs, [m]ax, or [s]um): ", conflict.describe());
+        io::stdout().flush().ok();
+        let mut answer = String::new();
+        match io::stdin().read_line(&mut answer) {
+            Ok(0) | Err(_) => return Err(format!("Merge error: no answer for {}", conflict.describe())),
+            Ok(_) => match conflict.choose(&answer) {
+                Ok(minutes) => return Ok(minutes),
+                Err(e) => eprintln!("{}", e),
+            },
+        }
+    }
+}
+
 // Print the error message to standard error and exit the process
 fn print_error_and_exit(error_msg: &str) {
     eprintln!("{}", error_msg);
@@ -138,14 +1227,186 @@ fn print_instructions() {
     let mut instr = String::new();
     instr.push_str("Valid tracker functions:\n\n");
     instr.push_str("FUNCTION <ARGUMENT>                DESCRIPTION\n");
-    instr.push_str("add <date> <activity> <minutes>    add minutes to an activity on a date\n");
+    instr.push_str("add <date> <activity> <minutes>    add minutes to a known activity on a date\n");
+    instr.push_str("add <date> <activity> <minutes> --new\n");
+    instr.push_str("                                   add minutes to a new activity on a date\n");
     instr.push_str("sub <date> <activity> <minutes>    subtract minutes from an activity on a date\n");
+    instr.push_str("set <date> <activity> <minutes>    set the minutes for an activity on a date\n");
+    instr.push_str("rm <date> [<activity>]             remove an activity (or all activities) from a date\n");
     instr.push_str("sum <date>                         print summary of activities on a date\n");
     instr.push_str("sum <start_date> <end_date>        print summary of activities from start date to end date\n");
+    instr.push_str("sum <range>                        print summary of activities in this-week, last-week, this-month, or last-month\n");
+    instr.push_str("compare <range_a> <range_b>        compare the totals of every activity in two date ranges\n");
+    instr.push_str("trend <activity> <range>           print 7/30-day moving averages, the slope, and unusual days of an activity\n");
+    instr.push_str("report --html <file> <range>       write the summary, weekly bar chart, and heatmaps of a date range to an HTML file\n");
+    instr.push_str("tui                                browse the calendar and add, subtract, set, or remove time in a full-screen view\n");
+    instr.push_str("shell [--autosave]                 enter functions line by line (with history and tab completion), saving on exit\n");
+    instr.push_str("                                   (or after every function with --autosave)\n");
+    instr.push_str("rename <old> <new>                 rename an activity on every date, summing with existing minutes\n");
+    instr.push_str("merge <activity>... --into <new>   merge several activities into one on every date\n");
+    instr.push_str("export --format csv|ics [<start> [<end>]]\n");
+    instr.push_str("                                   print the tracker data (optionally only a date range) as CSV or iCalendar\n");
+    instr.push_str("import <file>                      merge date,activity,minutes rows from a CSV file\n");
+    instr.push_str("import <file> --format <format>    merge time from another time tracker (timewarrior, toggl, org, or ics)\n");
+    instr.push_str("merge-file <file>                  combine another tracker data file into this one\n");
+    instr.push_str("sync [<remote>]                    pull from a git remote, merge the tracker data, and push (default origin)\n");
+    instr.push_str("undo [<n>]                         revert the last n operations (default 1)\n");
+    instr.push_str("redo [<n>]                         reapply the last n undone operations (default 1)\n");
+    instr.push_str("history [<n>]                      list the last n operations with timestamps (default 10)\n");
+    instr.push_str("compact                            fold the event log back into the data file\n");
+    instr.push_str("encrypt                            encrypt the data file with a passphrase (asked for, or from TRACKER_PASSPHRASE)\n");
+    instr.push_str("decrypt                            store the data file unencrypted again\n");
+    instr.push_str("config [<setting> [<value>]]       print all settings, print a setting, or change a setting\n");
+    instr.push_str("migrate --to <mode>                move the tracker data to another storage mode\n");
+    instr.push_str("activities                         list known activities with first/last used dates and totals\n");
+    instr.push_str("alias <alias> <activity>           let alias stand for an activity in other functions\n");
+    instr.push_str("unalias <alias>                    remove an alias\n");
+    instr.push_str("tag <activity> <category>          put an activity in a category for the subtotals in sum\n");
+    instr.push_str("untag <activity>                   take an activity out of its category\n");
+    instr.push_str("goal set <activity> at-least|at-most <minutes> day|week|month\n");
+    instr.push_str("                                   set a goal for an activity in every day, week, or month\n");
+    instr.push_str("goal rm <activity> [<period>]      remove the goals (or the goal for one period) of an activity\n");
+    instr.push_str("goals [--history <n>]              print progress towards goals, and hits and misses in the last n periods (default 4)\n");
+    instr.push_str("chart <activity>|all <start_date> <end_date>\n");
+    instr.push_str("                                   print a bar chart and sparkline of the minutes (stacked by activity for all)\n");
+    instr.push_str("heatmap <activity> [--year <year>] print a calendar of the minutes on every day of a year (default this year)\n");
+    instr.push_str("streaks [<activity>]               print current and longest streaks of days (or goal periods) with time\n");
+    instr.push_str("profile list                       list the profiles and which one is the default\n");
+    instr.push_str("profile create|delete <name>       create a profile, or delete one not in use (--force if it still has files)\n");
+    instr.push_str("profile default [<name>]           print or change the profile used without --profile\n");
+    instr.push_str("\nOptions for every function:\n");
+    instr.push_str("    --profile <name> (use the files of a profile instead of the default profile)\n");
+    instr.push_str("\nOptions for sum:\n");
+    instr.push_str("    --all-profiles (add together the activities of every profile)\n");
+    instr.push_str("    --of-day (also print the share of the 24 hours in a day spent on each activity)\n");
+    instr.push_str("\nOptions for rename and merge:\n");
+    instr.push_str("    --range <start_date> <end_date> (only change dates from start date to end date)\n");
+    instr.push_str("    --preview (print the affected days without saving the changes)\n");
+    instr.push_str("\nOptions for import:\n");
+    instr.push_str("    --policy add|replace|skip (what to do with activities that already have minutes, default add)\n");
+    instr.push_str("    --dry-run (print what would change without saving the changes)\n");
+    instr.push_str("    --new (create any activities that are not known yet)\n");
+    instr.push_str("    --map <event>=<activity> (for ics, import only events with this summary as the activity, can be repeated)\n");
+    instr.push_str("\nOptions for chart:\n");
+    instr.push_str("    --by day|week|month (the period of each bar, default day)\n");
+    instr.push_str("    --width <columns> (the width to fit the chart in, default the terminal width or 80)\n");
+    instr.push_str("\nOptions for heatmap:\n");
+    instr.push_str("    --no-color (draw shade blocks instead of colors, which is also done when not printing to a terminal)\n");
+    instr.push_str("\nOptions for streaks:\n");
+    instr.push_str("    --min <minutes> (the minutes needed in each period, default 1 or the at-least goals of each activity)\n");
+    instr.push_str("    --period day|week|month (the periods in a streak, default day or the periods of the goals)\n");
+    instr.push_str("\nOptions for merge-file and sync:\n");
+    instr.push_str("    --policy max|sum|ours|theirs|interactive (how to resolve days with different minutes, default max)\n");
+    instr.push_str("    --dry-run (merge-file only, print the conflicts without saving the changes)\n");
+    instr.push_str("\nSettings:\n");
+    instr.push_str("    storage json (rewrite the whole data file after every change, the default)\n");
+    instr.push_str("    storage log (append each change to an event log instead, use \"compact\" to fold it back)\n");
+    instr.push_str("    storage sqlite (keep the data in a SQLite database, needs the \"sqlite\" feature)\n");
+    instr.push_str("    git on (commit the data file to its git repository after every change, off by default)\n");
+    instr.push_str("\nNote: Date range arguments are a start date and an end date, or this-week, last-week, this-month, or last-month\n");
     instr.push_str("\nNote: Date arguments should use one of the following formats:\n");
     instr.push_str("    <year>-<month>-<day> (a date literal, such as 2023-5-31)\n");
     instr.push_str("    today (gives today's date)\n");
     instr.push_str("    today-<n> (gives a date n days before today)\n");
     instr.push_str("    today+<n> (gives a date n days after today)");
     println!("{}", instr);
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use tracker::activity_registry::ActivityRegistry;
+    use tracker::encryption::Cipher;
+    use tracker::goals::Goals;
+    use tracker::journal::Journal;
+    use tracker::profiles::Profiles;
+    use tracker::settings::Settings;
+    use tracker::storage::MemoryStorage;
+    use tracker::tracker_data::TrackerData;
+    use crate::{Session, run_function};
+
+    // Returns a session that only exists in memory, as the shell would have after opening a profile
+    fn session() -> Session {
+        Session {
+            profiles: Profiles::new(Path::new("")),
+            profile_dir: PathBuf::new(),
+            settings: Settings::new(),
+            storage: Box::new(MemoryStorage::new()),
+            tracker_data: TrackerData::new(),
+            saved: TrackerData::new(),
+            registry: ActivityRegistry::new(),
+            journal: Journal::new(),
+            goals: Goals::new(),
+            descriptions: Vec::new(),
+        }
+    }
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn failed_function_rolls_back() {
+        let mut session = session();
+        assert_eq!(run_function(&mut session, "add", args("2023-6-1 guitar 30 --new")), Ok(true));
+
+        // Setting too many minutes fails after the new activity was registered, so the registration is undone too
+        assert!(run_function(&mut session, "set", args("2023-6-1 drums 2000 --new")).is_err());
+        assert_eq!(session.registry.names(), vec![String::from("guitar")]);
+        assert_eq!(session.tracker_data.entries().len(), 1);
+        assert_eq!(session.descriptions, vec![String::from("add 30 guitar on 2023-06-01")]);
+
+        // The next function still works on the session
+        assert_eq!(run_function(&mut session, "add", args("2023-6-1 guitar 15")), Ok(true));
+        assert_eq!(session.tracker_data.entries()[0].2, 45);
+    }
+
+    #[test]
+    fn rename_in_range() {
+        let mut session = session();
+        assert_eq!(run_function(&mut session, "add", args("2023-6-1 guitar 30 --new")), Ok(true));
+        assert_eq!(run_function(&mut session, "add", args("2023-7-1 guitar 45")), Ok(true));
+        assert_eq!(run_function(&mut session, "alias", args("g guitar")), Ok(true));
+
+        // Guitar still has minutes outside the range, so it keeps its alias
+        assert_eq!(run_function(&mut session, "rename", args("guitar bass --range 2023-6-1 2023-6-30")), Ok(true));
+        assert_eq!(session.registry.resolve("g"), String::from("guitar"));
+        assert!(session.registry.names().contains(&String::from("bass")));
+
+        // Once all of its minutes are renamed, its alias moves to the new activity
+        assert_eq!(run_function(&mut session, "rename", args("guitar bass")), Ok(true));
+        assert_eq!(session.registry.resolve("g"), String::from("bass"));
+        assert!(!session.registry.names().contains(&String::from("guitar")));
+    }
+
+    #[test]
+    fn failed_migrate_keeps_settings() {
+        let mut session = session();
+        session.tracker_data.set_cipher(Some(Cipher::new("correct horse").unwrap()));
+
+        // An encrypted profile cannot be migrated, and the storage setting stays as it was for the next save
+        assert_eq!(run_function(&mut session, "migrate", args("--to log")),
+                   Err(String::from("Migrate error: the data file is encrypted (use \"decrypt\" first)")));
+        assert_eq!(session.settings.get("storage"), Ok(String::from("json")));
+        assert!(run_function(&mut session, "config", args("storage log")).is_err());
+        assert_eq!(session.settings.get("storage"), Ok(String::from("json")));
+    }
+
+    #[test]
+    fn profile_in_use_not_deleted() {
+        let root = std::env::temp_dir().join("tracker_profile_in_use_test");
+        let _ = std::fs::remove_dir_all(&root);
+        let mut session = session();
+        session.profiles = Profiles::new(&root);
+        assert_eq!(session.profiles.create("office"), Ok(()));
+        session.profile_dir = session.profiles.dir("office").unwrap();
+
+        // The session would write its files into the deleted directory when it is saved, so deleting it is refused
+        assert_eq!(run_function(&mut session, "profile", args("delete office --force")),
+                   Err(String::from("Profile error: \"office\" is the profile in use (delete it from another profile)")));
+        assert!(session.profile_dir.is_dir());
+        session.profile_dir = root.clone();
+        assert_eq!(run_function(&mut session, "profile", args("delete office --force")), Ok(false));
+        assert!(!root.join("tracker_profiles").join("office").exists());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
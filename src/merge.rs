@@ -0,0 +1,332 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::date::Date;
+use crate::tracker_data::TrackerData;
+
+// The minutes of some activities on a date
+pub type DayMinutes = BTreeMap<String, u16>;
+
+// The most minutes an activity can have on a date
+const MINUTES_PER_DAY: u16 = 60 * 24;
+
+// Enum Definition
+// How to resolve a date with different minutes in two tracker data files
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum MergePolicy {
+    Max,
+    Sum,
+    Ours,
+    Theirs,
+    Interactive,
+}
+
+// Struct Definition
+// A date with different minutes in our tracker data and theirs, along with the minutes of every activity that differs
+// on each side (an activity one side does not have is left out of that side)
+#[derive(Debug, PartialEq, Clone)]
+pub struct Conflict {
+    pub date: Date,
+    pub ours: DayMinutes,
+    pub theirs: DayMinutes,
+}
+
+// Public Methods
+impl MergePolicy {
+    // Creates a new MergePolicy from a string argument ("max", "sum", "ours", "theirs", or "interactive")
+    pub fn new_from_string(policy_str: &str) -> Result<MergePolicy, String> {
+        match policy_str {
+            "max" => Ok(MergePolicy::Max),
+            "sum" => Ok(MergePolicy::Sum),
+            "ours" => Ok(MergePolicy::Ours),
+            "theirs" => Ok(MergePolicy::Theirs),
+            "interactive" => Ok(MergePolicy::Interactive),
+            p => Err(format!("Merge policy error: \"{}\" is not one of max, sum, ours, theirs, interactive", p)),
+        }
+    }
+
+    // Returns the minutes of the activities this policy resolves a conflict to (None for interactive, which has to ask)
+    // Max and sum combine each activity separately, and sums are capped at the minutes in a day
+    pub fn resolve(self: &Self, conflict: &Conflict) -> Option<DayMinutes> {
+        let combine = |f: fn(u16, u16) -> u16| -> DayMinutes {
+            conflict.activities().into_iter()
+                .map(|activity| {
+                    let minutes = f(conflict.ours.get(&activity).copied().unwrap_or(0),
+                                    conflict.theirs.get(&activity).copied().unwrap_or(0));
+                    (activity, minutes)
+                })
+                .collect()
+        };
+        match self {
+            MergePolicy::Max => Some(combine(|a, b| a.max(b))),
+            MergePolicy::Sum => Some(combine(|a, b| a.saturating_add(b).min(MINUTES_PER_DAY))),
+            MergePolicy::Ours => Some(conflict.ours.clone()),
+            MergePolicy::Theirs => Some(conflict.theirs.clone()),
+            MergePolicy::Interactive => None,
+        }
+    }
+}
+
+impl Conflict {
+    // Returns every activity that differs on this date (on either side)
+    pub fn activities(self: &Self) -> BTreeSet<String> {
+        self.ours.keys().chain(self.theirs.keys()).cloned().collect()
+    }
+
+    // Returns the minutes chosen by an answer to a prompt: a policy (or its first letter)
+    pub fn choose(self: &Self, answer: &str) -> Result<DayMinutes, String> {
+        let answer = answer.trim().to_lowercase();
+        let policy = match answer.as_str() {
+            "m" | "max" => MergePolicy::Max,
+            "s" | "sum" => MergePolicy::Sum,
+            "o" | "ours" => MergePolicy::Ours,
+            "t" | "theirs" => MergePolicy::Theirs,
+            a => return Err(format!("Merge error: \"{}\" is not one of max, sum, ours, or theirs", a)),
+        };
+        Ok(policy.resolve(self).unwrap_or(self.ours.clone()))
+    }
+
+    // Returns a description (as a String) of this Conflict, such as "2023-6-1: ours guitar 30; theirs guitar 45, work 60"
+    pub fn describe(self: &Self) -> String {
+        format!("{}: ours {}; theirs {}", self.date.to_string(), describe_minutes(&self.ours), describe_minutes(&self.theirs))
+    }
+
+    // Returns whether the minutes an activity was resolved to are fewer than the sum of both sides, because the sum was
+    // more than the minutes in a day
+    pub fn capped(self: &Self, minutes: &DayMinutes) -> bool {
+        minutes.iter().any(|(activity, m)| {
+            *m == MINUTES_PER_DAY && self.ours.get(activity).copied().unwrap_or(0) as u32
+                                     + self.theirs.get(activity).copied().unwrap_or(0) as u32 > MINUTES_PER_DAY as u32
+        })
+    }
+}
+
+// Merges their tracker data into ours: dates only they have are added, and dates where both have different minutes
+// are conflicts, which are resolved by the policy (or by asking choose, for interactive) and returned with the minutes
+// they were resolved to
+// Nothing is changed if any conflict cannot be resolved
+pub fn merge_tracker_data(ours: &mut TrackerData, theirs: &TrackerData, policy: MergePolicy,
+                          choose: &mut dyn FnMut(&Conflict) -> Result<DayMinutes, String>) -> Result<Vec<(Conflict, DayMinutes)>, String> {
+    let ours_days = days(ours);
+    let mut merged = ours.clone();
+    let mut resolved: Vec<(Conflict, DayMinutes)> = Vec::new();
+    for (date, theirs_day) in days(theirs) {
+        let ours_day = ours_days.get(&date).cloned().unwrap_or_default();
+        let activities: BTreeSet<&String> = ours_day.keys().chain(theirs_day.keys()).collect();
+        let differing: Vec<&String> = activities.into_iter().filter(|a| ours_day.get(*a) != theirs_day.get(*a)).collect();
+        if ours_day.is_empty() || differing.is_empty() {
+            for (activity, minutes) in theirs_day {
+                merged.set(date.clone(), activity, minutes)?;
+            }
+            continue;
+        }
+        let conflict = Conflict {
+            date: date.clone(),
+            ours: ours_day.iter().filter(|(a, _)| differing.contains(a)).map(|(a, m)| (a.clone(), *m)).collect(),
+            theirs: theirs_day.iter().filter(|(a, _)| differing.contains(a)).map(|(a, m)| (a.clone(), *m)).collect(),
+        };
+        resolve_conflict(&mut merged, conflict, policy, choose, &mut resolved)?;
+    }
+    *ours = merged;
+    Ok(resolved)
+}
+
+// Merges the changes both sides made since their common base into ours: activities only one side changed take that
+// side's minutes, and dates with activities both sides changed differently are conflicts resolved like in
+// merge_tracker_data (an activity removed on one side is left out of that side)
+pub fn merge_three_way(base: &TrackerData, ours: &mut TrackerData, theirs: &TrackerData, policy: MergePolicy,
+                       choose: &mut dyn FnMut(&Conflict) -> Result<DayMinutes, String>) -> Result<Vec<(Conflict, DayMinutes)>, String> {
+    let mut keys: BTreeMap<Date, BTreeSet<String>> = BTreeMap::new();
+    for tracker_data in [base, &*ours, theirs] {
+        for (date, activity, _) in tracker_data.entries() {
+            keys.entry(date).or_default().insert(activity);
+        }
+    }
+    let mut merged = ours.clone();
+    let mut resolved: Vec<(Conflict, DayMinutes)> = Vec::new();
+    for (date, activities) in keys {
+        let mut conflict = Conflict {date: date.clone(), ours: DayMinutes::new(), theirs: DayMinutes::new()};
+        for activity in activities {
+            let base_minutes = base.get(&date, &activity);
+            let ours_minutes = ours.get(&date, &activity);
+            let theirs_minutes = theirs.get(&date, &activity);
+            if ours_minutes == theirs_minutes || theirs_minutes == base_minutes {
+                continue;
+            }
+            if ours_minutes == base_minutes {
+                merged.set(date.clone(), activity, theirs_minutes.unwrap_or(0))?;
+                continue;
+            }
+            if let Some(m) = ours_minutes {
+                conflict.ours.insert(activity.clone(), m);
+            }
+            if let Some(m) = theirs_minutes {
+                conflict.theirs.insert(activity.clone(), m);
+            }
+        }
+        if !conflict.activities().is_empty() {
+            resolve_conflict(&mut merged, conflict, policy, choose, &mut resolved)?;
+        }
+    }
+    *ours = merged;
+    Ok(resolved)
+}
+
+// Returns a report (as a String) of the conflicts and the minutes they were resolved to
+pub fn conflict_report(resolved: &[(Conflict, DayMinutes)]) -> String {
+    if resolved.is_empty() {
+        return String::from("No conflicts");
+    }
+    let mut report = format!("{} conflicting day(s):\n\nDATE\t\tOURS\tTHEIRS\tRESULT\n", resolved.len());
+    for (conflict, minutes) in resolved {
+        let date_str = conflict.date.to_string();
+        let date_tab = if date_str.len() < 8 {"\t\t"} else {"\t"};
+        let note = if conflict.capped(minutes) {format!(" (capped at {})", MINUTES_PER_DAY)} else {String::new()};
+        report.push_str(&format!("{}{}{}\t{}\t{}{}\n", date_str, date_tab, describe_minutes(&conflict.ours),
+                                 describe_minutes(&conflict.theirs), describe_minutes(minutes), note));
+    }
+    report.trim_end_matches("\n").to_string()
+}
+
+// Resolves a conflict by the policy (or by asking choose, for interactive), setting the minutes of its activities in
+// the merged tracker data and recording it in resolved
+fn resolve_conflict(merged: &mut TrackerData, conflict: Conflict, policy: MergePolicy,
+                    choose: &mut dyn FnMut(&Conflict) -> Result<DayMinutes, String>,
+                    resolved: &mut Vec<(Conflict, DayMinutes)>) -> Result<(), String> {
+    let minutes = match policy.resolve(&conflict) {
+        Some(m) => m,
+        None => choose(&conflict)?,
+    };
+    for activity in conflict.activities() {
+        let m = minutes.get(&activity).copied().unwrap_or(0);
+        merged.set(conflict.date.clone(), activity, m)?;
+    }
+    resolved.push((conflict, minutes));
+    Ok(())
+}
+
+// Returns the minutes of every activity on every date of the tracker data
+fn days(tracker_data: &TrackerData) -> BTreeMap<Date, DayMinutes> {
+    let mut days: BTreeMap<Date, DayMinutes> = BTreeMap::new();
+    for (date, activity, minutes) in tracker_data.entries() {
+        days.entry(date).or_default().insert(activity, minutes);
+    }
+    days
+}
+
+// Returns a description (as a String) of the minutes of some activities, such as "guitar 30, work 60" (or "none")
+fn describe_minutes(minutes: &DayMinutes) -> String {
+    if minutes.is_empty() {
+        return String::from("none");
+    }
+    minutes.iter().map(|(activity, m)| format!("{} {}", activity, m)).collect::<Vec<String>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::merge::{MergePolicy, Conflict, DayMinutes, merge_tracker_data, merge_three_way, conflict_report};
+    use crate::tracker_data::TrackerData;
+    use crate::date::Date;
+
+    // Returns the minutes of some activities on a date
+    fn minutes(activities: &[(&str, u16)]) -> DayMinutes {
+        activities.iter().map(|(activity, m)| (activity.to_string(), *m)).collect()
+    }
+
+    // Returns our and their tracker data, which conflict on 2023-6-1 (on guitar, and on reading, which only they have)
+    fn ours_and_theirs() -> (TrackerData, TrackerData) {
+        let date1 = Date::new_from_string("2023-6-1").unwrap();
+        let date2 = Date::new_from_string("2023-6-2").unwrap();
+        let mut ours = TrackerData::new();
+        assert_eq!(ours.add(date1.clone(), String::from("guitar"), 30), Ok(()));
+        assert_eq!(ours.add(date1.clone(), String::from("school"), 120), Ok(()));
+        let mut theirs = TrackerData::new();
+        assert_eq!(theirs.add(date1.clone(), String::from("guitar"), 45), Ok(()));
+        assert_eq!(theirs.add(date1.clone(), String::from("reading"), 15), Ok(()));
+        assert_eq!(theirs.add(date1.clone(), String::from("school"), 120), Ok(()));
+        assert_eq!(theirs.add(date2.clone(), String::from("work"), 60), Ok(()));
+        (ours, theirs)
+    }
+
+    #[test]
+    fn policies() {
+        let date1 = Date::new_from_string("2023-6-1").unwrap();
+        let date2 = Date::new_from_string("2023-6-2").unwrap();
+        let mut never_asked = |_: &Conflict| -> Result<DayMinutes, String> { panic!("should not ask") };
+        for (policy, guitar, reading) in [(MergePolicy::Max, Some(45), Some(15)), (MergePolicy::Sum, Some(75), Some(15)),
+                                          (MergePolicy::Ours, Some(30), None), (MergePolicy::Theirs, Some(45), Some(15))] {
+            let (mut ours, theirs) = ours_and_theirs();
+            let resolved = merge_tracker_data(&mut ours, &theirs, policy, &mut never_asked).unwrap();
+            // The whole day is one conflict, with only the activities that differ
+            assert_eq!(resolved.len(), 1);
+            assert_eq!(resolved[0].0, Conflict {date: date1.clone(), ours: minutes(&[("guitar", 30)]),
+                                                theirs: minutes(&[("guitar", 45), ("reading", 15)])});
+            assert_eq!(ours.get(&date1, "guitar"), guitar);
+            assert_eq!(ours.get(&date1, "reading"), reading);
+            assert_eq!(ours.get(&date1, "school"), Some(120));
+            assert_eq!(ours.get(&date2, "work"), Some(60));
+        }
+        assert_eq!(MergePolicy::new_from_string("min"),
+                    Err(String::from("Merge policy error: \"min\" is not one of max, sum, ours, theirs, interactive")));
+    }
+
+    #[test]
+    fn capped_sum() {
+        // Sums of more than a day are capped (and reported), rather than failing the merge
+        let date = Date::new_from_string("2023-6-1").unwrap();
+        let mut ours = TrackerData::new();
+        assert_eq!(ours.add(date.clone(), String::from("work"), 900), Ok(()));
+        let mut theirs = TrackerData::new();
+        assert_eq!(theirs.add(date.clone(), String::from("work"), 800), Ok(()));
+        let mut never_asked = |_: &Conflict| -> Result<DayMinutes, String> { panic!("should not ask") };
+        let resolved = merge_tracker_data(&mut ours, &theirs, MergePolicy::Sum, &mut never_asked).unwrap();
+        assert_eq!(ours.get(&date, "work"), Some(1440));
+        assert_eq!(conflict_report(&resolved),
+                    String::from("1 conflicting day(s):\n\nDATE\t\tOURS\tTHEIRS\tRESULT\n2023-6-1\twork 900\twork 800\twork 1440 (capped at 1440)"));
+    }
+
+    #[test]
+    fn three_way() {
+        // Both sides start from the same base, then change different things (and guitar in different ways)
+        let date = Date::new_from_string("2023-6-1").unwrap();
+        let mut base = TrackerData::new();
+        assert_eq!(base.add(date.clone(), String::from("guitar"), 30), Ok(()));
+        assert_eq!(base.add(date.clone(), String::from("school"), 120), Ok(()));
+        assert_eq!(base.add(date.clone(), String::from("work"), 60), Ok(()));
+        let mut ours = base.clone();
+        assert_eq!(ours.set(date.clone(), String::from("guitar"), 40), Ok(()));
+        assert_eq!(ours.remove(date.clone(), Some(String::from("school"))), Ok(()));
+        let mut theirs = base.clone();
+        assert_eq!(theirs.set(date.clone(), String::from("guitar"), 50), Ok(()));
+        assert_eq!(theirs.set(date.clone(), String::from("work"), 90), Ok(()));
+        assert_eq!(theirs.add(date.clone(), String::from("reading"), 15), Ok(()));
+        let mut never_asked = |_: &Conflict| -> Result<DayMinutes, String> { panic!("should not ask") };
+        let resolved = merge_three_way(&base, &mut ours, &theirs, MergePolicy::Ours, &mut never_asked).unwrap();
+        assert_eq!(resolved, vec![(Conflict {date: date.clone(), ours: minutes(&[("guitar", 40)]), theirs: minutes(&[("guitar", 50)])},
+                                   minutes(&[("guitar", 40)]))]);
+        assert_eq!(ours.entries(), vec![(date.clone(), String::from("guitar"), 40),
+                                        (date.clone(), String::from("reading"), 15),
+                                        (date.clone(), String::from("work"), 90)]);
+    }
+
+    #[test]
+    fn interactive() {
+        // Answers choose the minutes, and a failed answer leaves our tracker data unchanged
+        let date1 = Date::new_from_string("2023-6-1").unwrap();
+        let (mut ours, theirs) = ours_and_theirs();
+        let resolved = merge_tracker_data(&mut ours, &theirs, MergePolicy::Interactive, &mut |c| c.choose("o")).unwrap();
+        assert_eq!(ours.get(&date1, "guitar"), Some(30));
+        assert_eq!(ours.get(&date1, "reading"), None);
+        assert_eq!(conflict_report(&resolved),
+                    String::from("1 conflicting day(s):\n\nDATE\t\tOURS\tTHEIRS\tRESULT\n2023-6-1\tguitar 30\tguitar 45, reading 15\tguitar 30"));
+        let (mut ours, theirs) = ours_and_theirs();
+        assert_eq!(merge_tracker_data(&mut ours, &theirs, MergePolicy::Interactive, &mut |c| c.choose("40")),
+                    Err(String::from("Merge error: \"40\" is not one of max, sum, ours, or theirs")));
+        assert_eq!(ours.entries().len(), 2);
+
+        // Answers can be policies or their first letters
+        let conflict = Conflict {date: date1.clone(), ours: minutes(&[("guitar", 30)]), theirs: minutes(&[("guitar", 45), ("reading", 15)])};
+        assert_eq!(conflict.choose("T"), Ok(minutes(&[("guitar", 45), ("reading", 15)])));
+        assert_eq!(conflict.choose("sum"), Ok(minutes(&[("guitar", 75), ("reading", 15)])));
+        assert_eq!(conflict.describe(), String::from("2023-6-1: ours guitar 30; theirs guitar 45, reading 15"));
+    }
+}
@@ -0,0 +1,200 @@
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use json::JsonValue;
+
+// The profile whose files are kept in the directory itself (where they were before there were profiles)
+pub const MAIN_PROFILE: &str = "main";
+
+// The subdirectory holding a directory of files for every other profile
+pub const PROFILES_DIR: &str = "tracker_profiles";
+
+// Struct Definition
+// The named profiles in a directory, each with its own data, settings, activities, and journal files,
+// and the profile used when none is given
+pub struct Profiles {
+    root: PathBuf,
+    default: String,
+}
+
+// Public Methods
+impl Profiles {
+    // Creates a new Profiles for the directory root, with the main profile as the default
+    pub fn new(root: &Path) -> Profiles {
+        Profiles {
+            root: root.to_path_buf(),
+            default: String::from(MAIN_PROFILE),
+        }
+    }
+
+    // Loads the JSON formatted default profile from file filename into this Profiles
+    pub fn load_from_file(self: &mut Self, filename: &str) -> Result<(), String> {
+        // Open the file and get the contents
+        let mut contents = String::new();
+        if let Ok(mut file) = File::open(filename) {
+            match file.read_to_string(&mut contents) {
+                Ok(_) => {}
+                Err(_) => {return Err(format!("Load from file error: cannot read \"{filename}\""));}
+            }
+        }
+        // Parse the contents and use them to populate this Profiles
+        if !contents.is_empty() {
+            match json::parse(&contents) {
+                Ok(parsed) => {
+                    if let Some(default) = parsed["default"].as_str() {
+                        self.default = default.to_string();
+                    }
+                }
+                Err(_) => {
+                    return Err(format!("Load from file error: cannot parse contents of \"{filename}\""));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Saves the JSON formatted default profile from this Profiles into the file filename
+    pub fn save_to_file(self: &Self, filename: &str) -> Result<(), String> {
+        let mut profiles_json = JsonValue::new_object();
+        profiles_json["default"] = self.default.as_str().into();
+        match File::create(filename) {
+            Ok(mut file) => {
+                match file.write_all(profiles_json.pretty(4).as_bytes()) {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err(format!("Save to file error: cannot write to \"{filename}\"")),
+                }
+            }
+            Err(_) => Err(format!("Save to file error: cannot open \"{filename}\"")),
+        }
+    }
+
+    // Returns the names of every profile, starting with the main profile
+    pub fn names(self: &Self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        if let Ok(entries) = fs::read_dir(self.root.join(PROFILES_DIR)) {
+            for entry in entries.flatten() {
+                if entry.path().is_dir() {
+                    names.push(entry.file_name().to_string_lossy().to_string());
+                }
+            }
+        }
+        names.sort();
+        names.insert(0, String::from(MAIN_PROFILE));
+        names
+    }
+
+    // Returns the directory holding the files of a profile
+    pub fn dir(self: &Self, name: &str) -> Result<PathBuf, String> {
+        if name == MAIN_PROFILE {
+            Ok(self.root.clone())
+        } else if self.names().iter().any(|n| n == name) {
+            Ok(self.root.join(PROFILES_DIR).join(name))
+        } else {
+            Err(format!("Profile error: unknown profile \"{}\" (create it with \"profile create {}\")", name, name))
+        }
+    }
+
+    // Returns the profile used when none is given
+    pub fn default_profile(self: &Self) -> String {
+        self.default.clone()
+    }
+
+    // Makes an existing profile the one used when none is given
+    pub fn set_default(self: &mut Self, name: &str) -> Result<(), String> {
+        self.dir(name)?;
+        self.default = name.to_string();
+        Ok(())
+    }
+
+    // Creates a new, empty profile
+    pub fn create(self: &Self, name: &str) -> Result<(), String> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(format!("Profile error: \"{}\" is not a valid profile name (use letters, digits, - and _)", name));
+        }
+        if self.names().iter().any(|n| n == name) {
+            return Err(format!("Profile error: profile \"{}\" already exists", name));
+        }
+        fs::create_dir_all(self.root.join(PROFILES_DIR).join(name))
+            .map_err(|_| format!("Profile error: cannot create directory for profile \"{}\"", name))
+    }
+
+    // Deletes a profile, which must not be the main or default profile
+    // A profile that still has files is only deleted (along with them) if force is set
+    pub fn delete(self: &Self, name: &str, force: bool) -> Result<(), String> {
+        if name == MAIN_PROFILE {
+            return Err(format!("Profile error: the {} profile cannot be deleted", MAIN_PROFILE));
+        }
+        let dir = self.dir(name)?;
+        if name == self.default {
+            return Err(format!("Profile error: \"{}\" is the default profile (change the default first)", name));
+        }
+        let has_files = fs::read_dir(&dir).map(|mut entries| entries.next().is_some()).unwrap_or(false);
+        if has_files && !force {
+            return Err(format!("Profile error: profile \"{}\" still has files (use --force to delete them too)", name));
+        }
+        fs::remove_dir_all(&dir).map_err(|_| format!("Profile error: cannot delete directory of profile \"{}\"", name))
+    }
+
+    // Returns a list (as a String) of every profile, the directory of its files, and whether it is the default
+    pub fn describe(self: &Self) -> String {
+        let mut list = String::from("PROFILE\t\tDEFAULT\tDIRECTORY\n");
+        for name in self.names() {
+            let name_tab = if name.len() < 8 {"\t\t"} else {"\t"};
+            let default = if name == self.default {"yes"} else {""};
+            let dir = match self.dir(&name) {
+                Ok(d) if d.as_os_str().is_empty() => String::from("."),
+                Ok(d) => d.display().to_string(),
+                Err(_) => String::new(),
+            };
+            list.push_str(&format!("{}{}{}\t{}\n", name, name_tab, default, dir));
+        }
+        list.trim_end_matches("\n").to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::profiles::{Profiles, MAIN_PROFILE, PROFILES_DIR};
+
+    #[test]
+    fn create_delete_default() {
+        let root: PathBuf = std::env::temp_dir().join("tracker_profiles_test");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        let mut profiles = Profiles::new(&root);
+        assert_eq!(profiles.names(), vec![String::from(MAIN_PROFILE)]);
+        assert_eq!(profiles.dir(MAIN_PROFILE), Ok(root.clone()));
+
+        // New profiles get their own directory, and names must be usable as directory names
+        assert_eq!(profiles.create("work"), Ok(()));
+        assert_eq!(profiles.create("personal"), Ok(()));
+        assert_eq!(profiles.names(), vec![String::from("main"), String::from("personal"), String::from("work")]);
+        assert_eq!(profiles.dir("work"), Ok(root.join(PROFILES_DIR).join("work")));
+        assert_eq!(profiles.create("work"), Err(String::from("Profile error: profile \"work\" already exists")));
+        assert!(profiles.create("../work").is_err());
+        assert_eq!(profiles.dir("school"),
+                    Err(String::from("Profile error: unknown profile \"school\" (create it with \"profile create school\")")));
+
+        // The default is saved, and cannot be deleted while it is the default
+        assert_eq!(profiles.set_default("work"), Ok(()));
+        assert!(profiles.set_default("school").is_err());
+        let filename = root.join("tracker_profiles.json");
+        profiles.save_to_file(filename.to_str().unwrap()).unwrap();
+        let mut loaded = Profiles::new(&root);
+        loaded.load_from_file(filename.to_str().unwrap()).unwrap();
+        assert_eq!(loaded.default_profile(), String::from("work"));
+        assert!(loaded.describe().ends_with(&format!("work\t\tyes\t{}", root.join(PROFILES_DIR).join("work").display())));
+        assert_eq!(loaded.delete("work", false),
+                    Err(String::from("Profile error: \"work\" is the default profile (change the default first)")));
+        assert!(loaded.delete(MAIN_PROFILE, true).is_err());
+
+        // Profiles with files need to be forced to be deleted
+        std::fs::write(root.join(PROFILES_DIR).join("personal").join("tracker_data.json"), "{}").unwrap();
+        assert_eq!(loaded.delete("personal", false),
+                    Err(String::from("Profile error: profile \"personal\" still has files (use --force to delete them too)")));
+        assert_eq!(loaded.delete("personal", true), Ok(()));
+        assert_eq!(loaded.names(), vec![String::from("main"), String::from("work")]);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
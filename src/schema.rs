@@ -0,0 +1,120 @@
+use std::fs;
+use std::path::Path;
+use json::JsonValue;
+
+// The format version written by this version of tracker
+// Version 1 is the original unversioned map of dates to activities, and version 2 wraps it as
+// {"version": 2, "metadata": {...}, "data": {...}}
+pub const FORMAT_VERSION: u32 = 2;
+
+// A step that upgrades a parsed data file from one format version to the next
+type Migration = fn(&JsonValue) -> Result<JsonValue, String>;
+
+// The migrations between format versions, where MIGRATIONS[i] upgrades version i + 1 to version i + 2
+const MIGRATIONS: [Migration; 1] = [
+    upgrade_v1_to_v2,
+];
+
+// Returns the format version of a parsed data file (files without a "version" field are version 1)
+pub fn format_version(tracker_json: &JsonValue) -> Result<u32, String> {
+    if !tracker_json.has_key("version") {
+        return Ok(1);
+    }
+    match tracker_json["version"].as_u32() {
+        Some(v) if v >= 1 => Ok(v),
+        _ => Err(format!("Schema error: \"{}\" cannot be interpreted as a format version", tracker_json["version"])),
+    }
+}
+
+// Upgrades a parsed data file to the current format version, returning it along with the version it was upgraded from
+// Files written by a newer format version are refused, since this version of tracker cannot know what they contain
+pub fn upgrade(tracker_json: &JsonValue) -> Result<(JsonValue, u32), String> {
+    let original_version = format_version(tracker_json)?;
+    if original_version > FORMAT_VERSION {
+        return Err(format!("Schema error: format version {} is newer than the newest version this tracker understands ({})",
+                           original_version, FORMAT_VERSION));
+    }
+    let mut upgraded = tracker_json.clone();
+    for migration in &MIGRATIONS[(original_version - 1) as usize..] {
+        upgraded = migration(&upgraded)?;
+    }
+    Ok((upgraded, original_version))
+}
+
+// Returns the name of the backup kept of a data file in an older format version when it is upgraded
+pub fn backup_filename(filename: &str, version: u32) -> String {
+    format!("{}.v{}.bak", filename, version)
+}
+
+// Removes the backups kept of a data file in older format versions (such as once it is encrypted, since they are
+// plaintext copies), returning the names of the removed files
+pub fn remove_backups(filename: &str) -> Result<Vec<String>, String> {
+    let mut removed: Vec<String> = Vec::new();
+    for version in 1..FORMAT_VERSION {
+        let backup_filename = backup_filename(filename, version);
+        if Path::new(&backup_filename).exists() {
+            fs::remove_file(&backup_filename)
+                .map_err(|_| format!("Schema error: cannot remove backup \"{}\"", backup_filename))?;
+            removed.push(backup_filename);
+        }
+    }
+    Ok(removed)
+}
+
+// Returns a data file in the current format version with the given metadata and data sections
+pub fn wrap(metadata: JsonValue, data: JsonValue) -> JsonValue {
+    json::object!{
+        version: FORMAT_VERSION,
+        metadata: metadata,
+        data: data
+    }
+}
+
+// Version 1 -> 2: move the map of dates to activities into a "data" section next to a version and metadata
+fn upgrade_v1_to_v2(tracker_json: &JsonValue) -> Result<JsonValue, String> {
+    if !tracker_json.is_object() {
+        return Err(String::from("Schema error: version 1 data file is not a JSON object"));
+    }
+    Ok(json::object!{
+        version: 2,
+        metadata: JsonValue::new_object(),
+        data: tracker_json.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::{FORMAT_VERSION, format_version, upgrade, wrap, backup_filename, remove_backups};
+
+    #[test]
+    fn upgrading() {
+        // Unversioned files are version 1 and are wrapped in the current format
+        let v1 = json::object!{"2023-2-1": json::object!{guitar: 30}};
+        assert_eq!(format_version(&v1), Ok(1));
+        let (upgraded, from_version) = upgrade(&v1).unwrap();
+        assert_eq!(from_version, 1);
+        assert_eq!(upgraded, wrap(json::object!{}, v1.clone()));
+        assert_eq!(format_version(&upgraded), Ok(FORMAT_VERSION));
+
+        // Files already in the current format are unchanged
+        assert_eq!(upgrade(&upgraded), Ok((upgraded.clone(), FORMAT_VERSION)));
+
+        // Files from newer versions and unreadable versions are refused
+        assert_eq!(upgrade(&json::object!{version: 99, data: json::object!{}}),
+                    Err(format!("Schema error: format version 99 is newer than the newest version this tracker understands ({})", FORMAT_VERSION)));
+        assert_eq!(format_version(&json::object!{version: "two"}),
+                    Err(String::from("Schema error: \"two\" cannot be interpreted as a format version")));
+    }
+
+    #[test]
+    fn backups() {
+        let filename = std::env::temp_dir().join("tracker_backups_test.json");
+        let filename = filename.to_str().unwrap();
+        let backup = backup_filename(filename, 1);
+        assert_eq!(backup, format!("{}.v1.bak", filename));
+        std::fs::write(&backup, "{}").unwrap();
+        assert_eq!(remove_backups(filename), Ok(vec![backup.clone()]));
+        assert_eq!(std::path::Path::new(&backup).exists(), false);
+        assert_eq!(remove_backups(filename), Ok(Vec::new()));
+    }
+}
@@ -0,0 +1,136 @@
+use std::fs::File;
+use std::io::prelude::*;
+use std::collections::HashMap;
+use json::JsonValue;
+
+// The storage modes that can be used (sqlite is only available when built with the "sqlite" feature)
+#[cfg(feature = "sqlite")]
+pub const STORAGE_MODES: &[&str] = &["json", "log", "sqlite"];
+#[cfg(not(feature = "sqlite"))]
+pub const STORAGE_MODES: &[&str] = &["json", "log"];
+
+// The known settings, their default values, and the values they are allowed to take
+const KNOWN_SETTINGS: [(&str, &str, &[&str]); 2] = [
+    ("storage", "json", STORAGE_MODES),
+    ("git", "off", &["off", "on"]),
+];
+
+// Struct Definition
+#[derive(Clone)]
+pub struct Settings {
+    values: HashMap<String, String>,
+}
+
+// Public Methods
+impl Settings {
+    // Creates a new Settings with every setting at its default value
+    pub fn new() -> Settings {
+        let mut values: HashMap<String, String> = HashMap::new();
+        for (key, default, _) in KNOWN_SETTINGS {
+            values.insert(key.to_string(), default.to_string());
+        }
+        Settings {
+            values
+        }
+    }
+
+    // Loads JSON formatted settings from file filename into this Settings
+    pub fn load_from_file(self: &mut Self, filename: &str) -> Result<(), String> {
+        // Open the file and get the contents
+        let mut contents = String::new();
+        if let Ok(mut file) = File::open(filename) {
+            match file.read_to_string(&mut contents) {
+                Ok(_) => {}
+                Err(_) => {return Err(format!("Load from file error: cannot read \"{filename}\""));}
+            }
+        }
+        // Parse the contents and use them to populate this Settings (ignoring anything unknown)
+        if !contents.is_empty() {
+            match json::parse(&contents) {
+                Ok(parsed) => {
+                    for (key, value) in parsed.entries() {
+                        if let Some(v) = value.as_str() {
+                            if self.set(key, v).is_err() {
+                                return Err(format!("Load from file error: invalid value \"{v}\" for setting \"{key}\" in \"{filename}\""));
+                            }
+                        }
+                    }
+                }
+                Err(_) => {
+                    return Err(format!("Load from file error: cannot parse contents of \"{filename}\""));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Saves JSON formatted settings from this Settings into the file filename
+    pub fn save_to_file(self: &Self, filename: &str) -> Result<(), String> {
+        let mut settings_json = JsonValue::new_object();
+        for (key, _, _) in KNOWN_SETTINGS {
+            settings_json[key] = self.values[key].as_str().into();
+        }
+        match File::create(filename) {
+            Ok(mut file) => {
+                match file.write_all(settings_json.pretty(4).as_bytes()) {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err(format!("Save to file error: cannot write to \"{filename}\"")),
+                }
+            }
+            Err(_) => Err(format!("Save to file error: cannot open \"{filename}\"")),
+        }
+    }
+
+    // Returns the value of a setting
+    pub fn get(self: &Self, key: &str) -> Result<String, String> {
+        match self.values.get(key) {
+            Some(value) => Ok(value.clone()),
+            None => Err(format!("Settings error: unknown setting \"{}\"", key)),
+        }
+    }
+
+    // Changes the value of a setting
+    pub fn set(self: &mut Self, key: &str, value: &str) -> Result<(), String> {
+        match KNOWN_SETTINGS.iter().find(|(k, _, _)| *k == key) {
+            Some((_, _, allowed)) => {
+                if !allowed.contains(&value) {
+                    return Err(format!("Settings error: \"{}\" must be one of {}", key, allowed.join(", ")));
+                }
+                self.values.insert(key.to_string(), value.to_string());
+                Ok(())
+            }
+            None => Err(format!("Settings error: unknown setting \"{}\"", key)),
+        }
+    }
+
+    // Returns a list (as a String) of every setting and its value
+    pub fn describe(self: &Self) -> String {
+        let mut list = String::from("SETTING\t\tVALUE\n");
+        for (key, _, _) in KNOWN_SETTINGS {
+            let key_tab = if key.len() < 8 {"\t\t"} else {"\t"};
+            list.push_str(&format!("{}{}{}\n", key, key_tab, self.values[key]));
+        }
+        list.trim_end_matches("\n").to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::settings::Settings;
+
+    #[test]
+    fn get_set() {
+        let mut settings = Settings::new();
+        assert_eq!(settings.get("storage"), Ok(String::from("json")));
+        assert_eq!(settings.set("storage", "log"), Ok(()));
+        assert_eq!(settings.get("storage"), Ok(String::from("log")));
+        assert_eq!(settings.get("git"), Ok(String::from("off")));
+        assert_eq!(settings.set("git", "yes"),
+                    Err(String::from("Settings error: \"git\" must be one of off, on")));
+        assert!(settings.set("storage", "paper").unwrap_err().starts_with("Settings error: \"storage\" must be one of json, log"));
+        assert_eq!(settings.set("color", "blue"),
+                    Err(String::from("Settings error: unknown setting \"color\"")));
+        assert_eq!(settings.get("color"),
+                    Err(String::from("Settings error: unknown setting \"color\"")));
+    }
+}
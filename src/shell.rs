@@ -0,0 +1,140 @@
+use rustyline::completion::{Completer, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+
+// The functions that can be entered in the shell (completed at the start of a line)
+pub const FUNCTIONS: [&str; 36] = ["activities", "add", "alias", "chart", "compact", "compare", "config", "decrypt", "encrypt",
+                                   "exit", "export", "goal", "goals", "heatmap", "help", "history", "import", "merge",
+                                   "merge-file", "migrate", "profile", "redo", "rename", "report", "rm", "set", "streaks",
+                                   "sub", "sum", "sync", "tag", "trend", "tui", "unalias", "undo", "untag"];
+
+// The words that stand for dates (completed along with the activities)
+pub const DATE_KEYWORDS: [&str; 1] = ["today"];
+
+// The words that stand for date ranges, and the functions that accept them (the only ones they are completed after)
+pub const RANGE_KEYWORDS: [&str; 4] = ["this-week", "last-week", "this-month", "last-month"];
+pub const RANGE_FUNCTIONS: [&str; 4] = ["compare", "report", "sum", "trend"];
+
+// Struct Definition
+// Completes function names at the start of a line, and activity names, aliases, and the date keywords the function
+// accepts after that, when tab is pressed in the shell
+pub struct ShellHelper {
+    names: Vec<String>,
+}
+
+// Public Methods
+impl ShellHelper {
+    // Creates a new ShellHelper completing the activity names and aliases in names
+    pub fn new(names: Vec<String>) -> ShellHelper {
+        ShellHelper {names}
+    }
+
+    // Changes the activity names and aliases that are completed (such as after an activity is created)
+    pub fn set_names(self: &mut Self, names: Vec<String>) {
+        self.names = names;
+    }
+
+    // Returns where the word being typed at pos starts in line, along with the words it could be completed to
+    pub fn candidates(self: &Self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let start = line[..pos].rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let prefix = &line[start..pos];
+        let function = line[..start].split_whitespace().next();
+        let words: Vec<&str> = match function {
+            None => FUNCTIONS.to_vec(),
+            Some(f) if RANGE_FUNCTIONS.contains(&f) => {
+                self.names.iter().map(|n| n.as_str()).chain(DATE_KEYWORDS).chain(RANGE_KEYWORDS).collect()
+            }
+            Some(_) => self.names.iter().map(|n| n.as_str()).chain(DATE_KEYWORDS).collect(),
+        };
+        let mut candidates: Vec<String> = words.into_iter()
+            .filter(|word| word.starts_with(prefix))
+            .map(|word| word.to_string())
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+        (start, candidates)
+    }
+}
+
+impl Completer for ShellHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let (start, candidates) = self.candidates(line, pos);
+        Ok((start, candidates.into_iter().map(|c| Pair {display: c.clone(), replacement: c}).collect()))
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
+
+// Splits a line entered in the shell into words at whitespace, keeping whitespace inside double quotes (such as an
+// activity name with a space in it)
+pub fn split_line(line: &str) -> Result<Vec<String>, String> {
+    let mut words: Vec<String> = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut in_quotes = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                in_word = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_quotes {
+        return Err(String::from("Parse arguments error: missing closing quote"));
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::{ShellHelper, split_line};
+
+    #[test]
+    fn splitting() {
+        assert_eq!(split_line("  add today guitar 30 "), Ok(vec![String::from("add"), String::from("today"), String::from("guitar"), String::from("30")]));
+        assert_eq!(split_line("add today \"rock climbing\" 30 --new"),
+                    Ok(vec![String::from("add"), String::from("today"), String::from("rock climbing"), String::from("30"), String::from("--new")]));
+        assert_eq!(split_line("alias \"\" guitar").map(|w| w.len()), Ok(3));
+        assert_eq!(split_line("add \"guitar"), Err(String::from("Parse arguments error: missing closing quote")));
+    }
+
+    #[test]
+    fn completion() {
+        let helper = ShellHelper::new(vec![String::from("guitar"), String::from("g"), String::from("gym")]);
+        // Functions are completed at the start of a line, and activities and date keywords after that (with date range
+        // keywords only after the functions that accept them)
+        assert_eq!(helper.candidates("su", 2), (0, vec![String::from("sub"), String::from("sum")]));
+        assert_eq!(helper.candidates("add today gu", 12), (10, vec![String::from("guitar")]));
+        assert_eq!(helper.candidates("add g", 5), (4, vec![String::from("g"), String::from("guitar"), String::from("gym")]));
+        assert_eq!(helper.candidates("sum t", 5), (4, vec![String::from("this-month"), String::from("this-week"), String::from("today")]));
+        assert_eq!(helper.candidates("sum last-w", 10), (4, vec![String::from("last-week")]));
+        assert_eq!(helper.candidates("add t", 5), (4, vec![String::from("today")]));
+        assert_eq!(helper.candidates("compare this-week l", 19), (18, vec![String::from("last-month"), String::from("last-week")]));
+    }
+}
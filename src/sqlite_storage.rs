@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::date::Date;
+use crate::journal::JournalEntry;
+use crate::storage::Storage;
+use crate::tracker_data::TrackerData;
+
+// Struct Definition
+// Storage in a local SQLite database with one row per date and activity
+// Dates are stored as zero-padded ISO 8601 strings so that the date index can be used for range queries
+// The metadata section of the tracker data is kept as JSON text in a table of its own, so nothing is lost when
+// migrating to the database and back
+pub struct SqliteStorage {
+    connection: Connection,
+}
+
+// Public Methods
+impl SqliteStorage {
+    // Opens (or creates) the SQLite database in the file filename
+    pub fn open(filename: &str) -> Result<SqliteStorage, String> {
+        match Connection::open(filename) {
+            Ok(connection) => SqliteStorage::from_connection(connection),
+            Err(_) => Err(format!("SQLite error: cannot open \"{filename}\"")),
+        }
+    }
+
+    // Opens a new SQLite database that only exists in memory
+    pub fn open_in_memory() -> Result<SqliteStorage, String> {
+        match Connection::open_in_memory() {
+            Ok(connection) => SqliteStorage::from_connection(connection),
+            Err(_) => Err(String::from("SQLite error: cannot open in-memory database")),
+        }
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn load(self: &mut Self) -> Result<TrackerData, String> {
+        self.query("SELECT date, activity, minutes FROM entries", &[])
+    }
+
+    // Uses the date index to load only the rows in the range
+    fn load_range(self: &mut Self, start_date: &Date, end_date: &Date) -> Result<TrackerData, String> {
+        self.query("SELECT date, activity, minutes FROM entries WHERE date BETWEEN ?1 AND ?2",
+                   &[start_date.to_iso_string(), end_date.to_iso_string()])
+    }
+
+    // Adds up the minutes of every activity in the database, using the date index to find the rows in the range
+    fn activity_totals(self: &mut Self, start_date: &Date, end_date: &Date) -> Result<(BTreeMap<String, u32>, u32), String> {
+        let error = |_| String::from("SQLite error: cannot add up activity totals");
+        let range = [start_date.to_iso_string(), end_date.to_iso_string()];
+        let mut statement = self.connection.prepare("SELECT activity, SUM(minutes) FROM entries WHERE date BETWEEN ?1 AND ?2
+                                                     GROUP BY activity").map_err(error)?;
+        let rows = statement.query_map(params![range[0], range[1]], |row| Ok((row.get::<_, String>(0)?, row.get::<_, u32>(1)?)))
+            .map_err(error)?;
+        let mut totals: BTreeMap<String, u32> = BTreeMap::new();
+        for row in rows {
+            let (activity, minutes) = row.map_err(error)?;
+            totals.insert(activity, minutes);
+        }
+        let num_days = self.connection.query_row("SELECT COUNT(DISTINCT date) FROM entries WHERE date BETWEEN ?1 AND ?2",
+                                                 params![range[0], range[1]], |row| row.get::<_, u32>(0)).map_err(error)?;
+        Ok((totals, num_days))
+    }
+
+    // Replaces every row (and the metadata) in a single transaction
+    fn save(self: &mut Self, tracker_data: &TrackerData) -> Result<(), String> {
+        let error = |_| String::from("SQLite error: cannot save tracker data");
+        let transaction = self.connection.transaction().map_err(error)?;
+        transaction.execute("DELETE FROM metadata", []).map_err(error)?;
+        transaction.execute("INSERT INTO metadata (contents) VALUES (?1)", params![tracker_data.metadata().dump()]).map_err(error)?;
+        transaction.execute("DELETE FROM entries", []).map_err(error)?;
+        for (date, activity, minutes) in tracker_data.entries() {
+            transaction.execute("INSERT INTO entries (date, activity, minutes) VALUES (?1, ?2, ?3)",
+                                params![date.to_iso_string(), activity, minutes]).map_err(error)?;
+        }
+        transaction.commit().map_err(error)
+    }
+
+    // Updates only the changed rows in a single transaction
+    fn apply(self: &mut Self, _tracker_data: &TrackerData, entry: &JournalEntry) -> Result<(), String> {
+        let error = |_| format!("SQLite error: cannot apply \"{}\"", entry.description);
+        let transaction = self.connection.transaction().map_err(error)?;
+        for change in &entry.changes {
+            match change.after {
+                Some(minutes) => {
+                    transaction.execute("INSERT INTO entries (date, activity, minutes) VALUES (?1, ?2, ?3)
+                                         ON CONFLICT (date, activity) DO UPDATE SET minutes = excluded.minutes",
+                                        params![change.date.to_iso_string(), change.activity, minutes]).map_err(error)?;
+                }
+                None => {
+                    transaction.execute("DELETE FROM entries WHERE date = ?1 AND activity = ?2",
+                                        params![change.date.to_iso_string(), change.activity]).map_err(error)?;
+                }
+            }
+        }
+        transaction.commit().map_err(error)
+    }
+
+    fn applies_changes_only(self: &Self) -> bool {
+        true
+    }
+}
+
+// Private Methods
+impl SqliteStorage {
+    // Creates the table and date index if they do not exist yet
+    fn from_connection(connection: Connection) -> Result<SqliteStorage, String> {
+        let result = connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS entries (
+                 date TEXT NOT NULL,
+                 activity TEXT NOT NULL,
+                 minutes INTEGER NOT NULL,
+                 PRIMARY KEY (date, activity)
+             );
+             CREATE INDEX IF NOT EXISTS entries_by_date ON entries (date);
+             CREATE TABLE IF NOT EXISTS metadata (
+                 contents TEXT NOT NULL
+             );");
+        match result {
+            Ok(_) => Ok(SqliteStorage {connection}),
+            Err(_) => Err(String::from("SQLite error: cannot create tables")),
+        }
+    }
+
+    // Runs a query returning (date, activity, minutes) rows and collects them into a TrackerData (along with the metadata)
+    fn query(self: &Self, sql: &str, args: &[String]) -> Result<TrackerData, String> {
+        let error = |_| String::from("SQLite error: cannot load tracker data");
+        let mut statement = self.connection.prepare(sql).map_err(error)?;
+        let rows = statement.query_map(rusqlite::params_from_iter(args), |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, u16>(2)?))
+        }).map_err(error)?;
+        let mut tracker_data = TrackerData::new();
+        for row in rows {
+            let (date_str, activity, minutes) = row.map_err(error)?;
+            let date = Date::new_from_string(&date_str)
+                .map_err(|_| format!("SQLite error: cannot interpret \"{}\" as a date", date_str))?;
+            tracker_data.set(date, activity, minutes)?;
+        }
+        let metadata: Option<String> = self.connection.query_row("SELECT contents FROM metadata", [], |row| row.get(0))
+            .optional().map_err(error)?;
+        if let Some(contents) = metadata {
+            tracker_data.set_metadata(json::parse(&contents).map_err(|_| String::from("SQLite error: cannot parse metadata"))?);
+        }
+        Ok(tracker_data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sqlite_storage::SqliteStorage;
+    use crate::storage::{Storage, JsonFileStorage};
+    use crate::journal::JournalEntry;
+    use crate::tracker_data::TrackerData;
+    use crate::date::Date;
+
+    #[test]
+    fn save_apply_load() {
+        let mut storage = SqliteStorage::open_in_memory().unwrap();
+        let mut tracker_data = TrackerData::new();
+        let date1 = Date::new_from_string("2023-9-30").unwrap();
+        let date2 = Date::new_from_string("2023-10-1").unwrap();
+        assert_eq!(tracker_data.add(date1.clone(), String::from("guitar"), 30), Ok(()));
+        assert_eq!(storage.save(&tracker_data), Ok(()));
+
+        // Apply an add and a subtract that removes an activity
+        let before = tracker_data.clone();
+        assert_eq!(tracker_data.add(date2.clone(), String::from("school"), 60), Ok(()));
+        assert_eq!(tracker_data.subtract(date1.clone(), String::from("guitar"), 30), Ok(()));
+        assert_eq!(storage.apply(&tracker_data, &JournalEntry::new("add and sub", before.diff(&tracker_data))), Ok(()));
+        assert_eq!(storage.load().unwrap().entries(), tracker_data.entries());
+
+        // Range queries compare dates in date order (not string order of unpadded dates)
+        assert_eq!(storage.load_range(&date2, &date2).unwrap().entries(), vec![(date2.clone(), String::from("school"), 60)]);
+        assert_eq!(storage.load_range(&date1, &date1).unwrap().entries(), Vec::new());
+
+        // Totals are added up in the database
+        assert_eq!(tracker_data.add(date2.clone(), String::from("guitar"), 15), Ok(()));
+        assert_eq!(storage.save(&tracker_data), Ok(()));
+        assert_eq!(storage.activity_totals(&date1, &date2), Ok(tracker_data.activity_totals(&date1, &date2)));
+        assert_eq!(storage.activity_totals(&date1, &date2).unwrap().1, 1);
+    }
+
+    #[test]
+    fn round_trip() {
+        // JSON -> SQLite -> JSON gives back exactly the same file contents, metadata included
+        let mut tracker_data = TrackerData::new();
+        tracker_data.set_metadata(json::object!{created_by: "tracker 0.0.1", note: "laptop"});
+        assert_eq!(tracker_data.add(Date::new_from_string("2023-2-1").unwrap(), String::from("guitar"), 30), Ok(()));
+        assert_eq!(tracker_data.add(Date::new_from_string("2023-2-1").unwrap(), String::from("school"), 180), Ok(()));
+        assert_eq!(tracker_data.add(Date::new_from_string("2023-12-31").unwrap(), String::from("work"), 1440), Ok(()));
+        let filename = std::env::temp_dir().join("tracker_sqlite_round_trip_test.json");
+        let filename = filename.to_str().unwrap();
+        assert_eq!(JsonFileStorage::new(filename).save(&tracker_data), Ok(()));
+        let contents = std::fs::read_to_string(filename).unwrap();
+        let mut storage = SqliteStorage::open_in_memory().unwrap();
+        assert_eq!(storage.save(&JsonFileStorage::new(filename).load().unwrap()), Ok(()));
+        assert_eq!(JsonFileStorage::new(filename).save(&storage.load().unwrap()), Ok(()));
+        assert_eq!(std::fs::read_to_string(filename).unwrap(), contents);
+        std::fs::remove_file(filename).unwrap();
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::BTreeMap;
+
+use crate::date::Date;
+use crate::encryption::Cipher;
+use crate::event_log::EventLogStorage;
+use crate::journal::JournalEntry;
+use crate::tracker_data::TrackerData;
+#[cfg(feature = "sqlite")]
+use crate::sqlite_storage::SqliteStorage;
+
+// Trait Definition
+// A place that TrackerData can be loaded from and saved to
+pub trait Storage {
+    // Loads the tracker data (missing data gives an empty TrackerData)
+    fn load(self: &mut Self) -> Result<TrackerData, String>;
+
+    // Loads only the tracker data from start_date to end_date
+    // Storage that can look up a date range directly should override this, by default everything is loaded
+    fn load_range(self: &mut Self, start_date: &Date, end_date: &Date) -> Result<TrackerData, String> {
+        Ok(self.load()?.range(start_date, end_date))
+    }
+
+    // Returns the total minutes of every activity from start_date to end_date, along with the number of dates with data
+    // Storage that can add them up itself should override this, by default the date range is loaded and added up
+    fn activity_totals(self: &mut Self, start_date: &Date, end_date: &Date) -> Result<(BTreeMap<String, u32>, u32), String> {
+        Ok(self.load_range(start_date, end_date)?.activity_totals(start_date, end_date))
+    }
+
+    // Returns whether apply only writes the entries that changed, so that tracker data loaded with load_range can be
+    // changed and applied without loading the rest of the data (by default apply saves the whole tracker data)
+    fn applies_changes_only(self: &Self) -> bool {
+        false
+    }
+
+    // Saves the whole tracker data, replacing whatever was stored before
+    fn save(self: &mut Self, tracker_data: &TrackerData) -> Result<(), String>;
+
+    // Saves a single operation that has already been applied to the tracker data
+    // Storage that can be updated incrementally should override this, by default the whole tracker data is saved
+    fn apply(self: &mut Self, tracker_data: &TrackerData, _entry: &JournalEntry) -> Result<(), String> {
+        self.save(tracker_data)
+    }
+}
+
+// Struct Definitions
+// Storage in a single JSON file that is rewritten on every save
+// The cipher of an encrypted file is kept, so that loading it again does not read the passphrase again
+pub struct JsonFileStorage {
+    filename: String,
+    cipher: Option<Cipher>,
+}
+
+// Storage in memory only, which is mostly useful for tests
+pub struct MemoryStorage {
+    tracker_data: TrackerData,
+}
+
+// Public Methods
+impl JsonFileStorage {
+    // Creates a new JsonFileStorage for the file filename
+    pub fn new(filename: &str) -> JsonFileStorage {
+        JsonFileStorage {
+            filename: filename.to_string(),
+            cipher: None,
+        }
+    }
+}
+
+impl MemoryStorage {
+    // Creates a new, empty MemoryStorage
+    pub fn new() -> MemoryStorage {
+        MemoryStorage {
+            tracker_data: TrackerData::new(),
+        }
+    }
+}
+
+impl Storage for JsonFileStorage {
+    fn load(self: &mut Self) -> Result<TrackerData, String> {
+        let mut tracker_data = TrackerData::new();
+        tracker_data.set_cipher(self.cipher.clone());
+        tracker_data.load_from_file(&self.filename)?;
+        self.cipher = tracker_data.cipher();
+        Ok(tracker_data)
+    }
+
+    fn save(self: &mut Self, tracker_data: &TrackerData) -> Result<(), String> {
+        tracker_data.save_to_file(&self.filename)?;
+        self.cipher = tracker_data.cipher();
+        Ok(())
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn load(self: &mut Self) -> Result<TrackerData, String> {
+        Ok(self.tracker_data.clone())
+    }
+
+    fn save(self: &mut Self, tracker_data: &TrackerData) -> Result<(), String> {
+        self.tracker_data = tracker_data.clone();
+        Ok(())
+    }
+}
+
+// Opens the storage for a storage mode ("json", "log", or "sqlite") using the given data file,
+// event log file, and database file
+pub fn open_storage(mode: &str, filename: &str, log_filename: &str, db_filename: &str) -> Result<Box<dyn Storage>, String> {
+    match mode {
+        "json" => Ok(Box::new(JsonFileStorage::new(filename))),
+        "log" => Ok(Box::new(EventLogStorage::new(filename, log_filename))),
+        #[cfg(feature = "sqlite")]
+        "sqlite" => Ok(Box::new(SqliteStorage::open(db_filename)?)),
+        #[cfg(not(feature = "sqlite"))]
+        "sqlite" => Err(format!("Storage error: cannot open \"{}\" because sqlite support was not built in (enable the \"sqlite\" feature)", db_filename)),
+        m => Err(format!("Storage error: unknown storage mode \"{}\"", m)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::storage::{Storage, JsonFileStorage, MemoryStorage, open_storage};
+    use crate::journal::JournalEntry;
+    use crate::tracker_data::TrackerData;
+    use crate::date::Date;
+
+    // Adds some time to the tracker data and saves the change to storage
+    fn add_and_apply(storage: &mut dyn Storage, tracker_data: &mut TrackerData, minutes: u16) {
+        let date = Date::new_from_string("2023-2-1").unwrap();
+        let before = tracker_data.clone();
+        assert_eq!(tracker_data.add(date, String::from("guitar"), minutes), Ok(()));
+        let entry = JournalEntry::new("add guitar", before.diff(tracker_data));
+        assert_eq!(storage.apply(tracker_data, &entry), Ok(()));
+    }
+
+    #[test]
+    fn memory() {
+        let mut storage = MemoryStorage::new();
+        let mut tracker_data = storage.load().unwrap();
+        add_and_apply(&mut storage, &mut tracker_data, 30);
+        let loaded = storage.load().unwrap();
+        assert_eq!(loaded.get(&Date::new_from_string("2023-2-1").unwrap(), "guitar"), Some(30));
+        let start_date = Date::new_from_string("2023-3-1").unwrap();
+        let end_date = Date::new_from_string("2023-4-1").unwrap();
+        assert_eq!(storage.load_range(&start_date, &end_date).unwrap().entries(), Vec::new());
+    }
+
+    #[test]
+    fn json_file() {
+        let filename = std::env::temp_dir().join("tracker_storage_test.json");
+        let filename = filename.to_str().unwrap();
+        let _ = std::fs::remove_file(filename);
+        let mut storage = JsonFileStorage::new(filename);
+        let mut tracker_data = storage.load().unwrap();
+        add_and_apply(&mut storage, &mut tracker_data, 30);
+        let loaded = JsonFileStorage::new(filename).load().unwrap();
+        assert_eq!(loaded.get(&Date::new_from_string("2023-2-1").unwrap(), "guitar"), Some(30));
+        std::fs::remove_file(filename).unwrap();
+    }
+
+    #[test]
+    fn event_log() {
+        let filename = std::env::temp_dir().join("tracker_storage_test_snapshot.json");
+        let log_filename = std::env::temp_dir().join("tracker_storage_test_events.jsonl");
+        let (filename, log_filename) = (filename.to_str().unwrap(), log_filename.to_str().unwrap());
+        let _ = std::fs::remove_file(filename);
+        let _ = std::fs::remove_file(log_filename);
+        let date = Date::new_from_string("2023-2-1").unwrap();
+
+        // Changes only go to the log until the data is saved
+        let mut storage = open_storage("log", filename, log_filename, "").unwrap();
+        let mut tracker_data = storage.load().unwrap();
+        add_and_apply(storage.as_mut(), &mut tracker_data, 30);
+        add_and_apply(storage.as_mut(), &mut tracker_data, 15);
+        assert_eq!(std::path::Path::new(filename).exists(), false);
+        let loaded = open_storage("log", filename, log_filename, "").unwrap().load().unwrap();
+        assert_eq!(loaded.get(&date, "guitar"), Some(45));
+
+        // Saving folds the log into the snapshot
+        assert_eq!(storage.save(&tracker_data), Ok(()));
+        assert_eq!(std::path::Path::new(log_filename).exists(), false);
+        let loaded = open_storage("json", filename, log_filename, "").unwrap().load().unwrap();
+        assert_eq!(loaded.get(&date, "guitar"), Some(45));
+        std::fs::remove_file(filename).unwrap();
+
+        // Unknown storage modes are rejected
+        assert!(open_storage("paper", filename, log_filename, "").is_err());
+    }
+}
@@ -0,0 +1,142 @@
+use crate::date::Date;
+use crate::goals::Period;
+use crate::tracker_data::TrackerData;
+
+// Struct Definition
+// A run of consecutive periods (days, weeks, or months) with at least a minimum number of minutes of an activity
+#[derive(Debug, PartialEq, Clone)]
+pub struct Streak {
+    pub start: Date,
+    pub end: Date,
+    pub length: u32,
+}
+
+// Returns the current streak (if the activity has one going) and the longest streak (the most recent one, if several
+// are as long) of periods with at least minimum minutes of an activity, up to the period containing today
+// The period containing today does not break the current streak until it is over, since there is still time to meet it
+pub fn find_streaks(tracker_data: &TrackerData, activity: &str, minimum: u32, period: Period, today: &Date)
+                    -> Result<(Option<Streak>, Option<Streak>), String> {
+    if minimum == 0 {
+        return Err(String::from("Streak error: the minimum must be at least 1 minute"));
+    }
+    let first_date = match tracker_data.activity_usage().remove(activity) {
+        Some((first, _, _)) => first,
+        None => return Ok((None, None)),
+    };
+    let current_start = period.bounds(today)?.0;
+    let mut runs: Vec<Streak> = Vec::new();
+    let mut run: Option<Streak> = None;
+    // Step through the periods one at a time, starting with the one containing the first date of the activity
+    let mut date = period.bounds(&first_date)?.0;
+    while date <= current_start {
+        let (start_date, end_date) = period.bounds(&date)?;
+        if tracker_data.total_minutes(activity, &start_date, &end_date) >= minimum {
+            let end = if end_date > *today {today.clone()} else {end_date.clone()};
+            run = match run {
+                Some(r) => Some(Streak {end, length: r.length + 1, ..r}),
+                None => Some(Streak {start: start_date.clone(), end, length: 1}),
+            };
+        } else if start_date != current_start {
+            runs.extend(run.take());
+        }
+        date = end_date.add_days(1)?;
+    }
+    let current = run.clone();
+    runs.extend(run);
+    let longest = runs.into_iter().max_by_key(|r| r.length);
+    Ok((current, longest))
+}
+
+// Returns a report (as a String) of the current and longest streaks for each (activity, minimum, period) target
+pub fn streaks_report(tracker_data: &TrackerData, targets: &[(String, u32, Period)], today: &Date) -> Result<String, String> {
+    if targets.is_empty() {
+        return Ok(String::from("No activities"));
+    }
+    let mut report = String::from("ACTIVITY\tMINIMUM\t\tCURRENT\tSINCE\t\tLONGEST\tDATES\n");
+    for (activity, minimum, period) in targets {
+        let (current, longest) = find_streaks(tracker_data, activity, *minimum, *period, today)?;
+        let activity_tab = if activity.len() < 8 {"\t\t"} else {"\t"};
+        let minimum_str = format!("{}/{}", minimum, period.to_string());
+        let minimum_tab = if minimum_str.len() < 8 {"\t\t"} else {"\t"};
+        let (current_length, since) = match current {
+            Some(s) => (s.length, s.start.to_string()),
+            None => (0, String::from("-")),
+        };
+        let since_tab = if since.len() < 8 {"\t\t"} else {"\t"};
+        let (longest_length, dates) = match longest {
+            Some(s) => (s.length, format!("{} to {}", s.start.to_string(), s.end.to_string())),
+            None => (0, String::from("-")),
+        };
+        report.push_str(&format!("{}{}{}{}{}\t{}{}{}\t{}\n", activity, activity_tab, minimum_str, minimum_tab,
+                                 current_length, since, since_tab, longest_length, dates));
+    }
+    Ok(report.trim_end_matches("\n").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::streaks::{Streak, find_streaks, streaks_report};
+    use crate::goals::Period;
+    use crate::tracker_data::TrackerData;
+    use crate::date::Date;
+
+    // Returns tracker data with guitar on 2023-5-29 to 2023-5-31 and 2023-6-2 to 2023-6-5 (but only 10 minutes on 2023-6-3)
+    fn guitar_data() -> TrackerData {
+        let mut tracker_data = TrackerData::new();
+        for (date, minutes) in [("2023-5-29", 30), ("2023-5-30", 45), ("2023-5-31", 30),
+                                ("2023-6-2", 30), ("2023-6-3", 10), ("2023-6-4", 60), ("2023-6-5", 30)] {
+            assert_eq!(tracker_data.add(Date::new_from_string(date).unwrap(), String::from("guitar"), minutes), Ok(()));
+        }
+        tracker_data
+    }
+
+    fn streak(start: &str, end: &str, length: u32) -> Streak {
+        Streak {start: Date::new_from_string(start).unwrap(), end: Date::new_from_string(end).unwrap(), length}
+    }
+
+    #[test]
+    fn daily() {
+        let tracker_data = guitar_data();
+        let today = Date::new_from_string("2023-6-5").unwrap();
+        assert_eq!(find_streaks(&tracker_data, "guitar", 1, Period::Day, &today),
+                    Ok((Some(streak("2023-6-2", "2023-6-5", 4)), Some(streak("2023-6-2", "2023-6-5", 4)))));
+        // With a higher minimum, the 10 minutes on 2023-6-3 break the streak, so the longest is the first one
+        assert_eq!(find_streaks(&tracker_data, "guitar", 30, Period::Day, &today),
+                    Ok((Some(streak("2023-6-4", "2023-6-5", 2)), Some(streak("2023-5-29", "2023-5-31", 3)))));
+        // Today does not break the current streak yet, but missing yesterday does
+        let tomorrow = Date::new_from_string("2023-6-6").unwrap();
+        assert_eq!(find_streaks(&tracker_data, "guitar", 30, Period::Day, &tomorrow).unwrap().0, Some(streak("2023-6-4", "2023-6-5", 2)));
+        let later = Date::new_from_string("2023-6-7").unwrap();
+        assert_eq!(find_streaks(&tracker_data, "guitar", 30, Period::Day, &later).unwrap().0, None);
+        assert_eq!(find_streaks(&tracker_data, "piano", 1, Period::Day, &today), Ok((None, None)));
+        assert!(find_streaks(&tracker_data, "guitar", 0, Period::Day, &today).is_err());
+    }
+
+    #[test]
+    fn equally_long() {
+        // Of two streaks that are as long, the most recent one is the longest
+        let mut tracker_data = TrackerData::new();
+        for date in ["2023-6-1", "2023-6-2", "2023-6-4", "2023-6-5"] {
+            assert_eq!(tracker_data.add(Date::new_from_string(date).unwrap(), String::from("guitar"), 30), Ok(()));
+        }
+        let today = Date::new_from_string("2023-6-10").unwrap();
+        assert_eq!(find_streaks(&tracker_data, "guitar", 1, Period::Day, &today), Ok((None, Some(streak("2023-6-4", "2023-6-5", 2)))));
+    }
+
+    #[test]
+    fn weekly() {
+        // The week of 2023-5-29 has 205 minutes, and the current week (which has 30 so far) does not break the streak yet
+        let tracker_data = guitar_data();
+        let today = Date::new_from_string("2023-6-7").unwrap();
+        assert_eq!(find_streaks(&tracker_data, "guitar", 60, Period::Week, &today),
+                    Ok((Some(streak("2023-5-29", "2023-6-4", 1)), Some(streak("2023-5-29", "2023-6-4", 1)))));
+        // Streaks that include the current week end today
+        assert_eq!(find_streaks(&tracker_data, "guitar", 30, Period::Week, &today),
+                    Ok((Some(streak("2023-5-29", "2023-6-7", 2)), Some(streak("2023-5-29", "2023-6-7", 2)))));
+        let targets = vec![(String::from("guitar"), 30, Period::Day), (String::from("piano"), 1, Period::Day)];
+        assert_eq!(streaks_report(&tracker_data, &targets, &today), Ok(String::from(
+            "ACTIVITY\tMINIMUM\t\tCURRENT\tSINCE\t\tLONGEST\tDATES\n\
+             guitar\t\t30/day\t\t0\t-\t\t3\t2023-5-29 to 2023-5-31\n\
+             piano\t\t1/day\t\t0\t-\t\t0\t-")));
+    }
+}
@@ -1,13 +1,44 @@
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::prelude::*;
-use std::collections::HashMap;
+use std::path::Path;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use json::JsonValue;
 
 use crate::date::Date;
+use crate::encryption::{self, Cipher};
+use crate::journal::Change;
+use crate::schema;
 
 // Struct Definition
+// Dates and activities are kept in order so that saving unchanged data always writes the same file
+// The metadata section of the data file is kept as it was loaded (so that unknown entries survive a save)
+// If the data file was encrypted, the cipher is kept so that it is encrypted again when saved
+#[derive(Clone)]
 pub struct TrackerData {
-    data: HashMap<Date, HashMap<String, u16>>
+    data: BTreeMap<Date, BTreeMap<String, u16>>,
+    metadata: JsonValue,
+    cipher: Option<Cipher>,
+}
+
+// Enum Definition
+// How imported minutes are combined with minutes already recorded for the same activity on the same date
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ConflictPolicy {
+    Add,
+    Replace,
+    Skip,
+}
+
+impl ConflictPolicy {
+    // Creates a new ConflictPolicy from a string argument ("add", "replace", or "skip")
+    pub fn new_from_string(policy_str: &str) -> Result<ConflictPolicy, String> {
+        match policy_str {
+            "add" => Ok(ConflictPolicy::Add),
+            "replace" => Ok(ConflictPolicy::Replace),
+            "skip" => Ok(ConflictPolicy::Skip),
+            p => Err(format!("Conflict policy error: \"{}\" is not one of add, replace, skip", p)),
+        }
+    }
 }
 
 // Public Methods
@@ -15,11 +46,15 @@ impl TrackerData {
     // Creates a new, default TrackerData
     pub fn new() -> TrackerData {
         TrackerData {
-            data: HashMap::new()
+            data: BTreeMap::new(),
+            metadata: JsonValue::new_object(),
+            cipher: None,
         }
     }
 
     // Loads JSON formatted data from file filename into this TrackerData
+    // An encrypted file is decrypted with the cipher of this TrackerData if it has the same key, or else with the
+    // passphrase read for it
     pub fn load_from_file(self: &mut Self, filename: &str) -> Result<(), String> {
         // Open the file and get the contents
         let mut contents = String::new();
@@ -32,12 +67,14 @@ impl TrackerData {
             },
             Err(_) => {}
         }
+        // Decrypt the contents if the file is encrypted
+        let plaintext = self.decrypt_contents(&contents, filename)?;
         // Parse the contents into a JsonValue object
         let parsed: json::JsonValue;
-        if contents == "" {
+        if plaintext == "" {
             parsed = JsonValue::new_object();
         } else {
-            match json::parse(&contents) {
+            match json::parse(&plaintext) {
                 Ok(p) => {
                     parsed = p;
                 }
@@ -46,19 +83,47 @@ impl TrackerData {
                 }
             }
         }
-        // Use the JsonValue object to populate this TrackerData struct
-        self.from_json(&parsed).unwrap_or_else(|_| {});
+        // Use the JsonValue object to populate this TrackerData struct (older format versions are only upgraded in memory,
+        // the file itself is upgraded when it is saved)
+        self.load_from_json(&parsed)
+            .map_err(|e| format!("Load from file error: cannot load \"{filename}\": {e}"))?;
         Ok(())
     }
 
-    // Saves JSON formatted data from this TrackerData into the file filename 
-    pub fn save_to_file(self: &mut Self, filename: &str) -> Result<(), String> {
+    // Loads JSON formatted data (in the same format as the data file) from a string into this TrackerData
+    pub fn load_from_string(self: &mut Self, contents: &str) -> Result<(), String> {
+        match json::parse(&self.decrypt_contents(contents, "the tracker data")?) {
+            Ok(parsed) => {
+                self.load_from_json(&parsed)?;
+                Ok(())
+            }
+            Err(_) => Err(String::from("Load from string error: cannot parse contents")),
+        }
+    }
+
+    // Saves JSON formatted data from this TrackerData into the file filename (unless the file was written by a newer
+    // format version, which would lose whatever that version added)
+    // Saving over a file in an older format version upgrades it, so a backup of the original file is kept first (unless
+    // this TrackerData is encrypted, since the backup would be a plaintext copy)
+    pub fn save_to_file(self: &Self, filename: &str) -> Result<(), String> {
+        if let Ok(contents) = fs::read_to_string(filename) {
+            let existing = json::parse(&contents).ok().filter(|_| !encryption::is_encrypted(&contents));
+            if let Some(version) = existing.and_then(|e| schema::format_version(&e).ok()) {
+                if version > schema::FORMAT_VERSION {
+                    return Err(format!("Save to file error: refusing to overwrite \"{filename}\", which uses newer format version {version}"));
+                }
+                let backup_filename = schema::backup_filename(filename, version);
+                if version < schema::FORMAT_VERSION && self.cipher.is_none() && !Path::new(&backup_filename).exists()
+                   && fs::write(&backup_filename, &contents).is_err() {
+                    return Err(format!("Save to file error: cannot write backup \"{backup_filename}\""));
+                }
+            }
+        }
         // Open the file for writing
         match File::create(filename) {
             Ok(mut file) => {
                 // Write the JSON to the file
-                let tracker_json = self.to_json()?;
-                match file.write_all(tracker_json.pretty(4).as_bytes()) {
+                match file.write_all(self.save_to_string()?.as_bytes()) {
                     Ok(_) => {}
                     Err(_) => {
                         return Err(format!("Save to file error: cannot write to \"{filename}\""));
@@ -72,6 +137,44 @@ impl TrackerData {
         Ok(())
     }
 
+    // Returns the JSON formatted data (in the same format as the data file) from this TrackerData as a string
+    pub fn save_to_string(self: &Self) -> Result<String, String> {
+        // Note which version of tracker created and last wrote the file
+        let mut metadata = self.metadata.clone();
+        let written_by = format!("tracker {}", env!("CARGO_PKG_VERSION"));
+        if !metadata.has_key("created_by") {
+            metadata["created_by"] = written_by.as_str().into();
+        }
+        metadata["written_by"] = written_by.into();
+        encryption::encrypt_if(&self.cipher, schema::wrap(metadata, self.to_json()?).pretty(4))
+    }
+
+    // Returns the metadata section (as it is kept in memory, without the versions that are added when saving)
+    pub fn metadata(self: &Self) -> &JsonValue {
+        &self.metadata
+    }
+
+    // Replaces the metadata section (such as with metadata kept by a storage other than the data file)
+    pub fn set_metadata(self: &mut Self, metadata: JsonValue) {
+        self.metadata = metadata;
+    }
+
+    // Returns whether this TrackerData is encrypted when saved
+    pub fn is_encrypted(self: &Self) -> bool {
+        self.cipher.is_some()
+    }
+
+    // Returns the cipher this TrackerData is encrypted with when saved (such as to load the other files of a profile,
+    // which are encrypted with the same key)
+    pub fn cipher(self: &Self) -> Option<Cipher> {
+        self.cipher.clone()
+    }
+
+    // Changes how this TrackerData is encrypted when saved (None saves it unencrypted)
+    pub fn set_cipher(self: &mut Self, cipher: Option<Cipher>) {
+        self.cipher = cipher;
+    }
+
     // Adds minutes to an activity on a date
     pub fn add(self: &mut Self, date: Date, activity: String, minutes: u16) -> Result<(), String> {
         // If we already have the date, update the data
@@ -89,7 +192,7 @@ impl TrackerData {
             self.data.insert(date, activities);
         // If we don't already have the date, create the data
         } else {
-            let mut activities: HashMap<String, u16> = HashMap::new();
+            let mut activities: BTreeMap<String, u16> = BTreeMap::new();
             activities.insert(activity, minutes);
             self.data.insert(date, activities);
         }
@@ -125,53 +228,354 @@ impl TrackerData {
         Ok(())
     }
 
-    // Returns a summary (as a String) of the activities for a given date or date range
-    pub fn summarize(self: &Self, start_date: Date, end_date: Date) -> Result<String, String> {
-        // Make sure start_date is before end_date
+    // Sets the minutes for an activity on a date (setting zero minutes removes the activity)
+    pub fn set(self: &mut Self, date: Date, activity: String, minutes: u16) -> Result<(), String> {
+        if minutes > 60 * 24 {
+            return Err(format!("Set error: minutes exceeds {} for {} on {}", 60 * 24, activity, date.to_string()));
+        }
+        if minutes == 0 {
+            self.set_minutes(&date, &activity, None);
+        } else {
+            self.set_minutes(&date, &activity, Some(minutes));
+        }
+        Ok(())
+    }
+
+    // Removes an activity from a date, or every activity from a date if no activity is given
+    pub fn remove(self: &mut Self, date: Date, activity: Option<String>) -> Result<(), String> {
+        // If we don't already have the date, let the user know
+        if !self.data.contains_key(&date) {
+            return Err(format!("Remove error: no activities recorded for {}", date.to_string()));
+        }
+        match activity {
+            Some(activity) => {
+                // If we don't already have the activity, let the user know
+                if !self.data[&date].contains_key(&activity) {
+                    return Err(format!("Remove error: no minutes recorded for {} on {}", activity, date.to_string()));
+                }
+                self.set_minutes(&date, &activity, None);
+            }
+            None => {
+                self.data.remove(&date);
+            }
+        }
+        Ok(())
+    }
+
+    // Returns the minutes recorded for an activity on a date (if there are any)
+    pub fn get(self: &Self, date: &Date, activity: &str) -> Option<u16> {
+        self.data.get(date).and_then(|activities| activities.get(activity).copied())
+    }
+
+    // Returns every date, activity, and minutes recorded (sorted by date and activity)
+    pub fn entries(self: &Self) -> Vec<(Date, String, u16)> {
+        let mut entries: Vec<(Date, String, u16)> = Vec::new();
+        for (date, activities) in &self.data {
+            for (activity, minutes) in activities {
+                entries.push((date.clone(), activity.clone(), *minutes));
+            }
+        }
+        entries
+    }
+
+    // Returns the total minutes recorded for an activity from start_date to end_date
+    pub fn total_minutes(self: &Self, activity: &str, start_date: &Date, end_date: &Date) -> u32 {
         if end_date < start_date {
-            return Err(format!("Summarize error: end date {} is before start date {}", end_date.to_string(), start_date.to_string()));
+            return 0;
         }
-        // Collect the data from those dates
-        let mut num_days: u16 = 0;
-        let mut activities: HashMap<String, u16> = HashMap::new();
-        let mut curr_date = start_date.clone();
-        while curr_date <= end_date {
-            if self.data.contains_key(&curr_date) {
-                for (activity, minutes) in &self.data[&curr_date] {
-                    if activities.contains_key(activity) {
-                        activities.insert(activity.clone(), activities[activity] + *minutes);
-                    } else {
-                        activities.insert(activity.clone(), *minutes);
-                    }
+        self.data.range(start_date.clone()..=end_date.clone())
+            .filter_map(|(_, activities)| activities.get(activity))
+            .map(|minutes| *minutes as u32)
+            .sum()
+    }
+
+    // Returns a new TrackerData with only the dates from start_date to end_date
+    pub fn range(self: &Self, start_date: &Date, end_date: &Date) -> TrackerData {
+        let mut data: BTreeMap<Date, BTreeMap<String, u16>> = BTreeMap::new();
+        for (date, activities) in &self.data {
+            if date >= start_date && date <= end_date {
+                data.insert(date.clone(), activities.clone());
+            }
+        }
+        TrackerData {
+            data,
+            metadata: self.metadata.clone(),
+            cipher: self.cipher.clone(),
+        }
+    }
+
+    // Returns the changes (sorted by date and activity) needed to turn this TrackerData into other
+    pub fn diff(self: &Self, other: &TrackerData) -> Vec<Change> {
+        let mut changes: Vec<Change> = Vec::new();
+        for (date, activities) in &self.data {
+            for (activity, minutes) in activities {
+                let after = other.get(date, activity);
+                if after != Some(*minutes) {
+                    changes.push(Change {date: date.clone(), activity: activity.clone(), before: Some(*minutes), after});
                 }
-                num_days += 1;
             }
-            curr_date = curr_date.add_days(1).unwrap();
+        }
+        for (date, activities) in &other.data {
+            for (activity, minutes) in activities {
+                if self.get(date, activity).is_none() {
+                    changes.push(Change {date: date.clone(), activity: activity.clone(), before: None, after: Some(*minutes)});
+                }
+            }
+        }
+        changes.sort_by(|a, b| (&a.date, &a.activity).cmp(&(&b.date, &b.activity)));
+        changes
+    }
+
+    // Applies changes to this TrackerData (or reverts them, if reverse is set)
+    pub fn apply_changes(self: &mut Self, changes: &[Change], reverse: bool) {
+        for change in changes {
+            let minutes = if reverse {change.before} else {change.after};
+            self.set_minutes(&change.date, &change.activity, minutes);
+        }
+    }
+
+    // Imports (date, activity, minutes) rows, using policy for activities that already have minutes recorded
+    // If there is an error (such as a row with more minutes than a day has), this TrackerData will not be changed
+    pub fn import(self: &mut Self, rows: &[(Date, String, u16)], policy: ConflictPolicy) -> Result<(), String> {
+        let mut imported = self.clone();
+        let mut replaced: HashSet<(Date, String)> = HashSet::new();
+        for (date, activity, minutes) in rows {
+            if *minutes > 60 * 24 {
+                return Err(format!("Import error: minutes exceeds {} for {} on {}", 60 * 24, activity, date.to_string()));
+            }
+            let existing = self.get(date, activity).is_some();
+            match policy {
+                // Only the first row replaces the existing minutes, later rows for the same activity add to it
+                ConflictPolicy::Replace if existing && replaced.insert((date.clone(), activity.clone())) => {
+                    imported.set(date.clone(), activity.clone(), *minutes)?
+                }
+                ConflictPolicy::Skip if existing => {}
+                _ => imported.add(date.clone(), activity.clone(), *minutes)?,
+            }
+        }
+        self.data = imported.data;
+        Ok(())
+    }
+
+    // Returns the total minutes of every activity from start_date to end_date, along with the number of dates with data
+    pub fn activity_totals(self: &Self, start_date: &Date, end_date: &Date) -> (BTreeMap<String, u32>, u32) {
+        let mut num_days: u32 = 0;
+        let mut activities: BTreeMap<String, u32> = BTreeMap::new();
+        if end_date < start_date {
+            return (activities, num_days);
+        }
+        for (_, day_activities) in self.data.range(start_date.clone()..=end_date.clone()) {
+            for (activity, minutes) in day_activities {
+                *activities.entry(activity.clone()).or_insert(0) += *minutes as u32;
+            }
+            num_days += 1;
+        }
+        (activities, num_days)
+    }
+
+    // Returns a summary (as a String) of the activities for a given date or date range, with each activity's share of
+    // all the time in the range (and of the 24 hours in a day if of_day is set), followed by subtotals for the
+    // categories of the activities (from categories, which maps activities to their categories) if there are any
+    pub fn summarize(self: &Self, start_date: Date, end_date: Date, categories: &HashMap<String, String>, of_day: bool)
+                     -> Result<String, String> {
+        let (activities, num_days) = self.activity_totals(&start_date, &end_date);
+        TrackerData::summarize_totals(start_date, end_date, &activities, num_days, categories, of_day)
+    }
+
+    // Returns the total minutes of every activity from start_date to end_date in several TrackerData added together
+    // (such as the profiles of one person, so the totals can go past the minutes in a day), along with the number of
+    // dates with data in any of them
+    pub fn combined_totals(tracker_datas: &[TrackerData], start_date: &Date, end_date: &Date) -> (BTreeMap<String, u32>, u32) {
+        let mut activities: BTreeMap<String, u32> = BTreeMap::new();
+        let mut dates: BTreeSet<&Date> = BTreeSet::new();
+        if end_date < start_date {
+            return (activities, 0);
+        }
+        for tracker_data in tracker_datas {
+            for (activity, minutes) in tracker_data.activity_totals(start_date, end_date).0 {
+                *activities.entry(activity).or_insert(0) += minutes;
+            }
+            dates.extend(tracker_data.data.range(start_date.clone()..=end_date.clone()).map(|(date, _)| date));
+        }
+        (activities, dates.len() as u32)
+    }
+
+    // Returns a summary like summarize, but of totals that were already collected (such as by combined_totals, or by
+    // storage that adds them up itself) over num_days dates with data
+    pub fn summarize_totals(start_date: Date, end_date: Date, activities: &BTreeMap<String, u32>, num_days: u32,
+                            categories: &HashMap<String, String>, of_day: bool) -> Result<String, String> {
+        // Make sure start_date is before end_date
+        if end_date < start_date {
+            return Err(format!("Summarize error: end date {} is before start date {}", end_date.to_string(), start_date.to_string()));
         }
         // If there is data for those dates, return a string representing that data
         if !activities.is_empty() {
-            let mut summary = format!("Summary from {} to {}:\n\n", start_date.to_string(), end_date.to_string());
-            summary.push_str("ACTIVITY\tTOTAL TIME\tAVG TIME\n");
+            let total: u32 = activities.values().sum();
+            let mut category_totals: BTreeMap<String, u32> = BTreeMap::new();
             for (activity, minutes) in activities {
-                let line: String;
-                let activity_tab = if activity.len() < 8 {"\t\t"} else {"\t"};
-                let minutes_tab = if minutes.to_string().len() < 8 {"\t\t"} else {"\t"};
-                line = format!("{}{}{}{}{}\n", activity, activity_tab, minutes, minutes_tab, minutes/num_days);
-                summary.push_str(&line);
+                if let Some(category) = categories.get(activity) {
+                    *category_totals.entry(category.clone()).or_insert(0) += minutes;
+                }
+            }
+            let mut summary = format!("Summary from {} to {}:\n\n", start_date.to_string(), end_date.to_string());
+            summary.push_str(&summary_table("ACTIVITY", activities, total, num_days, of_day));
+            if !category_totals.is_empty() {
+                summary.push_str("\n\n");
+                summary.push_str(&summary_table("CATEGORY", &category_totals, total, num_days, of_day));
             }
-            Ok(summary.trim_end_matches("\n").to_string())
+            Ok(summary)
         // If there is not data for those dates, return an error indicating that
         } else {
             Err(format!("Summarize error: no data for {} to {}", start_date.to_string(), end_date.to_string()))
         }
     }
+
+    // Returns the first date used, last date used, and lifetime total minutes for every recorded activity
+    pub fn activity_usage(self: &Self) -> HashMap<String, (Date, Date, u32)> {
+        let mut usage: HashMap<String, (Date, Date, u32)> = HashMap::new();
+        for (date, activities) in &self.data {
+            for (activity, minutes) in activities {
+                match usage.get_mut(activity) {
+                    Some((first, last, total)) => {
+                        if date < first {
+                            *first = date.clone();
+                        }
+                        if date > last {
+                            *last = date.clone();
+                        }
+                        *total += *minutes as u32;
+                    }
+                    None => {
+                        usage.insert(activity.clone(), (date.clone(), date.clone(), *minutes as u32));
+                    }
+                }
+            }
+        }
+        usage
+    }
+
+    // Renames an activity on every date (or only on dates in range), summing minutes with the new
+    // activity where both exist on the same day, and returns a preview (as a String) of the affected days
+    pub fn rename_activity(self: &mut Self, old: &str, new: &str, range: Option<(Date, Date)>) -> Result<String, String> {
+        if old == new {
+            return Err(format!("Rename error: {} and {} are the same activity", old, new));
+        }
+        let changes = self.combine_activities(&[old.to_string()], new, range, "Rename error")?;
+        let mut preview = format!("Renamed {} to {} on {} day(s):\n\n", old, new, changes.len());
+        for (date, before, after) in changes {
+            preview.push_str(&format!("{}: {} -> {}\n", date.to_string(), before, after));
+        }
+        Ok(preview.trim_end_matches("\n").to_string())
+    }
+
+    // Merges several activities into a target activity on every date (or only on dates in range),
+    // summing their minutes, and returns a preview (as a String) of the affected days
+    pub fn merge_activities(self: &mut Self, sources: &[String], target: &str, range: Option<(Date, Date)>) -> Result<String, String> {
+        let changes = self.combine_activities(sources, target, range, "Merge error")?;
+        let mut preview = format!("Merged {} into {} on {} day(s):\n\n", sources.join(", "), target, changes.len());
+        for (date, before, after) in changes {
+            preview.push_str(&format!("{}: {} -> {}\n", date.to_string(), before, after));
+        }
+        Ok(preview.trim_end_matches("\n").to_string())
+    }
 }
 
 // Private Methods
 impl TrackerData {
+    // Sets (or removes, if minutes is None) the minutes for an activity on a date, removing the date if it becomes empty
+    fn set_minutes(self: &mut Self, date: &Date, activity: &str, minutes: Option<u16>) {
+        match minutes {
+            Some(m) => {
+                self.data.entry(date.clone()).or_default().insert(activity.to_string(), m);
+            }
+            None => {
+                if let Some(activities) = self.data.get_mut(date) {
+                    activities.remove(activity);
+                    if activities.is_empty() {
+                        self.data.remove(date);
+                    }
+                }
+            }
+        }
+    }
+
+    // Moves the minutes of the source activities into the target activity on each date in range
+    // Returns the affected dates (sorted) along with a description of each date before and after the change
+    // If there is an error, this TrackerData will not be changed
+    fn combine_activities(self: &mut Self, sources: &[String], target: &str, range: Option<(Date, Date)>, error_prefix: &str) -> Result<Vec<(Date, String, String)>, String> {
+        // Make sure the range is valid
+        if let Some((start_date, end_date)) = &range {
+            if end_date < start_date {
+                return Err(format!("{}: end date {} is before start date {}", error_prefix, end_date.to_string(), start_date.to_string()));
+            }
+        }
+        // Every activity that ends up in the target, including the target itself
+        let mut names: Vec<String> = sources.to_vec();
+        names.push(target.to_string());
+        names.sort();
+        names.dedup();
+        // Work out the new activities for each affected date
+        let mut new_data = self.data.clone();
+        let mut changes: Vec<(Date, String, String)> = Vec::new();
+        for (date, activities) in &self.data {
+            if let Some((start_date, end_date)) = &range {
+                if date < start_date || date > end_date {
+                    continue;
+                }
+            }
+            if !sources.iter().any(|source| source != target && activities.contains_key(source)) {
+                continue;
+            }
+            let mut new_activities = activities.clone();
+            let mut before: Vec<String> = Vec::new();
+            let mut total_minutes: u32 = 0;
+            for name in &names {
+                if let Some(minutes) = new_activities.remove(name) {
+                    before.push(format!("{} {}", name, minutes));
+                    total_minutes += minutes as u32;
+                }
+            }
+            if total_minutes > 60 * 24 {
+                return Err(format!("{}: total minutes exceeds {} for {} on {}", error_prefix, 60 * 24, target, date.to_string()));
+            }
+            new_activities.insert(target.to_string(), total_minutes as u16);
+            new_data.insert(date.clone(), new_activities);
+            changes.push((date.clone(), before.join(", "), format!("{} {}", target, total_minutes)));
+        }
+        // Let the user know if nothing was found to change
+        if changes.is_empty() {
+            return match &range {
+                Some((start_date, end_date)) => Err(format!("{}: no minutes recorded for {} from {} to {}", error_prefix, sources.join(", "), start_date.to_string(), end_date.to_string())),
+                None => Err(format!("{}: no minutes recorded for {}", error_prefix, sources.join(", "))),
+            };
+        }
+        self.data = new_data;
+        changes.sort();
+        Ok(changes)
+    }
+
+    // Returns the decrypted contents of an encrypted file (keeping its cipher), or else the contents themselves
+    // A file encrypted with the key of the current cipher is decrypted with it, otherwise the passphrase for filename is read
+    fn decrypt_contents(self: &mut Self, contents: &str, filename: &str) -> Result<String, String> {
+        let (plaintext, cipher) = encryption::decrypt_if_encrypted(contents, &self.cipher, filename)?;
+        self.cipher = cipher;
+        Ok(plaintext)
+    }
+
+    // Upgrades a parsed data file to the current format version (refusing newer ones) and uses it to populate this
+    // TrackerData, returning the format version it was in
+    fn load_from_json(self: &mut Self, parsed: &JsonValue) -> Result<u32, String> {
+        let (upgraded, original_version) = schema::upgrade(parsed)?;
+        self.from_json(&upgraded["data"]).unwrap_or(());
+        if upgraded["metadata"].is_object() {
+            self.metadata = upgraded["metadata"].clone();
+        }
+        Ok(original_version)
+    }
+
     // Fills this TrackerData with the date from the JsonValue object
     fn from_json(self: &mut Self, tracker_json: &JsonValue) -> Result<(), String> {
-        let mut new_data: HashMap<Date, HashMap<String, u16>> = HashMap::new();
+        let mut new_data: BTreeMap<Date, BTreeMap<String, u16>> = BTreeMap::new();
         // Loop over all the key-value pairs in parsed
         for (date_str, activities_json) in tracker_json.entries() {
             // Get the date from the key
@@ -180,8 +584,8 @@ impl TrackerData {
                 Ok(d) => date = d,
                 Err(_) => continue,
             }
-            // Get the activities from the value, which is itself a HashMap
-            let mut activities: HashMap<String, u16> = HashMap::new();
+            // Get the activities from the value, which is itself a map
+            let mut activities: BTreeMap<String, u16> = BTreeMap::new();
             // Loop over all the key-value pairs in activities_json
             for (act_str, dur) in activities_json.entries() {
                 // Get the activity from the key
@@ -213,8 +617,8 @@ impl TrackerData {
     fn to_json(self: &Self) -> Result<JsonValue, String> {
         let mut tracker_json = JsonValue::new_object();
         for (date, activities) in &self.data {
-            // Get the string version of the date
-            let date_string = date.to_string();
+            // Get the zero-padded ISO 8601 version of the date (so the dates are in order in the file too)
+            let date_string = date.to_iso_string();
             // Get the JSON version of the activities
             let mut activities_json = JsonValue::new_object();
             for (activity, minutes) in activities {
@@ -239,20 +643,41 @@ impl TrackerData {
     }
 }
 
+// Returns a table (as a String) of the total, average (over num_days), and share of total for every name (an activity
+// or a category), with the share of the 24 hours in a day taken up by the average if of_day is set
+fn summary_table(heading: &str, totals: &BTreeMap<String, u32>, total: u32, num_days: u32, of_day: bool) -> String {
+    let mut table = format!("{}\tTOTAL TIME\tAVG TIME\tSHARE{}\n", heading, if of_day {"\t\tOF DAY"} else {""});
+    for (name, minutes) in totals {
+        let average = minutes / num_days;
+        let name_tab = if name.len() < 8 {"\t\t"} else {"\t"};
+        let minutes_tab = if minutes.to_string().len() < 8 {"\t\t"} else {"\t"};
+        let average_tab = if average.to_string().len() < 8 {"\t\t"} else {"\t"};
+        let mut line = format!("{}{}{}{}{}{}{:.1}%", name, name_tab, minutes, minutes_tab, average, average_tab,
+                               *minutes as f64 * 100.0 / total as f64);
+        if of_day {
+            line.push_str(&format!("\t\t{:.1}%", *minutes as f64 * 100.0 / (num_days * 60 * 24) as f64));
+        }
+        table.push_str(&line);
+        table.push('\n');
+    }
+    table.trim_end_matches("\n").to_string()
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::tracker_data::TrackerData;
+    use std::collections::HashMap;
+    use crate::tracker_data::{TrackerData, ConflictPolicy};
     use crate::date::Date;
 
     #[test]
     fn from_to_json() {
         // Create valid data
         let valid_json = json::object!{
-            "2023-2-1": json::object!{
+            "2023-02-01": json::object!{
                 guitar: 30,
                 school: 180
             },
-            "2023-3-1": json::object!{
+            "2023-03-01": json::object!{
                 school: 210,
                 work: 120
             }
@@ -266,14 +691,14 @@ mod tests {
 
         // Create invalid data
         let invalid_json = json::object!{
-            "2023-2-1": json::object!{
+            "2023-02-01": json::object!{
                 guitar: "hello"
             },
             "2023.3.1": json::object!{
                 school: 210,
                 work: 120
             },
-            "2023-4-1": json::array![
+            "2023-04-01": json::array![
                 "school",
                 60,
                 "guitar",
@@ -287,6 +712,102 @@ mod tests {
                     Ok(valid_json.clone()));
     }
 
+    #[test]
+    fn versioning() {
+        let filename = std::env::temp_dir().join("tracker_versioning_test.json");
+        let filename = filename.to_str().unwrap();
+        let backup_filename = format!("{}.v1.bak", filename);
+        let _ = std::fs::remove_file(&backup_filename);
+        let date = Date::new_from_string("2023-2-1").unwrap();
+
+        // Unversioned files are upgraded in memory on load, and only upgraded on disk (keeping a backup of the
+        // original) when they are first saved
+        let v1_contents = json::object!{"2023-2-1": json::object!{guitar: 30}}.pretty(4);
+        std::fs::write(filename, &v1_contents).unwrap();
+        let mut tracker_data = TrackerData::new();
+        assert_eq!(tracker_data.load_from_file(filename), Ok(()));
+        assert_eq!(tracker_data.get(&date, "guitar"), Some(30));
+        assert_eq!(std::path::Path::new(&backup_filename).exists(), false);
+        assert_eq!(std::fs::read_to_string(filename).unwrap(), v1_contents);
+        assert_eq!(tracker_data.save_to_file(filename), Ok(()));
+        assert_eq!(std::fs::read_to_string(&backup_filename).unwrap(), v1_contents);
+        assert_eq!(tracker_data.save_to_file(filename), Ok(()));
+        assert_eq!(std::fs::read_to_string(&backup_filename).unwrap(), v1_contents);
+
+        // Saves are in the current format and keep unknown metadata
+        let with_metadata = json::object!{version: 2, metadata: json::object!{note: "laptop"}, data: json::parse(&v1_contents).unwrap()};
+        std::fs::write(filename, with_metadata.pretty(4)).unwrap();
+        assert_eq!(tracker_data.load_from_file(filename), Ok(()));
+        assert_eq!(tracker_data.save_to_file(filename), Ok(()));
+        let saved = json::parse(&std::fs::read_to_string(filename).unwrap()).unwrap();
+        assert_eq!(saved["version"].as_u32(), Some(crate::schema::FORMAT_VERSION));
+        assert_eq!(saved["metadata"]["note"].as_str(), Some("laptop"));
+        assert_eq!(saved["data"]["2023-02-01"]["guitar"].as_u16(), Some(30));
+
+        // Files from newer versions are neither loaded nor overwritten
+        std::fs::write(filename, json::object!{version: 99, data: json::object!{}}.dump()).unwrap();
+        assert!(TrackerData::new().load_from_file(filename).unwrap_err().starts_with("Load from file error: cannot load"));
+        assert_eq!(tracker_data.save_to_file(filename),
+                    Err(format!("Save to file error: refusing to overwrite \"{}\", which uses newer format version 99", filename)));
+        std::fs::remove_file(filename).unwrap();
+        std::fs::remove_file(&backup_filename).unwrap();
+    }
+
+    #[test]
+    fn save_order() {
+        // The same data added in different orders saves to the same bytes, with dates in date order
+        let filename = std::env::temp_dir().join("tracker_save_order_test.json");
+        let filename = filename.to_str().unwrap();
+        let rows = [("2023-10-1", "work", 120), ("2023-2-1", "school", 180), ("2023-2-1", "guitar", 30)];
+        let mut saved: Vec<String> = Vec::new();
+        for order in [[0, 1, 2], [2, 1, 0]] {
+            let mut tracker_data = TrackerData::new();
+            for i in order {
+                let (date_str, activity, minutes) = rows[i];
+                assert_eq!(tracker_data.add(Date::new_from_string(date_str).unwrap(), String::from(activity), minutes), Ok(()));
+            }
+            assert_eq!(tracker_data.save_to_file(filename), Ok(()));
+            saved.push(std::fs::read_to_string(filename).unwrap());
+        }
+        assert_eq!(saved[0], saved[1]);
+        let positions: Vec<usize> = ["\"2023-02-01\"", "\"guitar\"", "\"school\"", "\"2023-10-01\""].iter()
+            .map(|key| saved[0].find(key).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+        std::fs::remove_file(filename).unwrap();
+    }
+
+    #[test]
+    fn encrypted_file() {
+        // Encrypting a file in an older format upgrades it without keeping a plaintext backup
+        let filename = std::env::temp_dir().join("tracker_encrypted_test.json");
+        let filename = filename.to_str().unwrap();
+        let backup_filename = crate::schema::backup_filename(filename, 1);
+        let _ = std::fs::remove_file(&backup_filename);
+        let date = Date::new_from_string("2023-2-1").unwrap();
+        std::fs::write(filename, json::object!{"2023-2-1": json::object!{therapy: 50}}.pretty(4)).unwrap();
+        let mut tracker_data = TrackerData::new();
+        assert_eq!(tracker_data.load_from_file(filename), Ok(()));
+        let cipher = crate::encryption::Cipher::new("correct horse").unwrap();
+        tracker_data.set_cipher(Some(cipher.clone()));
+        assert_eq!(tracker_data.save_to_file(filename), Ok(()));
+        assert!(!std::fs::read_to_string(filename).unwrap().contains("therapy"));
+        assert_eq!(std::path::Path::new(&backup_filename).exists(), false);
+
+        // An encrypted data file loads transparently with a cipher of the same key, and stays encrypted
+        let mut loaded = TrackerData::new();
+        loaded.set_cipher(Some(cipher));
+        assert_eq!(loaded.load_from_file(filename), Ok(()));
+        assert_eq!(loaded.get(&date, "therapy"), Some(50));
+        assert!(loaded.is_encrypted());
+
+        // Without the cipher, it is saved unencrypted again
+        loaded.set_cipher(None);
+        assert_eq!(loaded.save_to_file(filename), Ok(()));
+        assert!(std::fs::read_to_string(filename).unwrap().contains("therapy"));
+        std::fs::remove_file(filename).unwrap();
+    }
+
     #[test]
     fn add() {
         // Create a base TrackerData object to work with
@@ -385,27 +906,234 @@ mod tests {
         // Summarize a valid date range
         let date1 = Date::new_from_string("2023-2-1").unwrap();
         let date2 = Date::new_from_string("2023-4-1").unwrap();
-        let summary1 = "Summary from 2023-2-1 to 2023-4-1:
-
-ACTIVITY\tTOTAL TIME\tAVG TIME
-guitar\t\t30\t\t15
-school\t\t390\t\t195";
-        let summary2 = "Summary from 2023-2-1 to 2023-4-1:
+        let no_categories = HashMap::new();
+        assert_eq!(tracker_data.summarize(date1.clone(), date2.clone(), &no_categories, false), Ok(String::from(
+            "Summary from 2023-2-1 to 2023-4-1:\n\n\
+             ACTIVITY\tTOTAL TIME\tAVG TIME\tSHARE\n\
+             guitar\t\t30\t\t15\t\t7.1%\n\
+             school\t\t390\t\t195\t\t92.9%")));
 
-ACTIVITY\tTOTAL TIME\tAVG TIME
-school\t\t390\t\t195
-guitar\t\t30\t\t15";
-        let summarize_result = tracker_data.summarize(date1.clone(), date2.clone());
-        assert!(summarize_result.clone() == Ok(String::from(summary1)) ||
-                summarize_result.clone() == Ok(String::from(summary2)));
+        // Summarize with shares of the day and category subtotals
+        let categories = HashMap::from([(String::from("school"), String::from("learning"))]);
+        assert_eq!(tracker_data.summarize(date1.clone(), date2.clone(), &categories, true), Ok(String::from(
+            "Summary from 2023-2-1 to 2023-4-1:\n\n\
+             ACTIVITY\tTOTAL TIME\tAVG TIME\tSHARE\t\tOF DAY\n\
+             guitar\t\t30\t\t15\t\t7.1%\t\t1.0%\n\
+             school\t\t390\t\t195\t\t92.9%\t\t13.5%\n\n\
+             CATEGORY\tTOTAL TIME\tAVG TIME\tSHARE\t\tOF DAY\n\
+             learning\t390\t\t195\t\t92.9%\t\t13.5%")));
 
         // Try to summarize an invalid date range
-        assert_eq!(tracker_data.summarize(date2.clone(), date1.clone()),
+        assert_eq!(tracker_data.summarize(date2.clone(), date1.clone(), &no_categories, false),
                     Err(String::from("Summarize error: end date 2023-2-1 is before start date 2023-4-1")));
         
         // Try to summarize a date range with no data
         let date3 = Date::new_from_string("2023-5-1").unwrap();
-        assert_eq!(tracker_data.summarize(date2.clone(), date3.clone()),
+        assert_eq!(tracker_data.summarize(date2.clone(), date3.clone(), &no_categories, false),
                     Err(String::from("Summarize error: no data for 2023-4-1 to 2023-5-1")));
     }
+
+    #[test]
+    fn combined_totals() {
+        // Two profiles with 900 minutes of work on the same date add up to more than a day
+        let date1 = Date::new_from_string("2023-2-1").unwrap();
+        let date2 = Date::new_from_string("2023-2-2").unwrap();
+        let mut home = TrackerData::new();
+        assert_eq!(home.add(date1.clone(), String::from("work"), 900), Ok(()));
+        let mut office = TrackerData::new();
+        assert_eq!(office.add(date1.clone(), String::from("work"), 900), Ok(()));
+        assert_eq!(office.add(date2.clone(), String::from("guitar"), 60), Ok(()));
+        let profiles = vec![home, office];
+        let (activities, num_days) = TrackerData::combined_totals(&profiles, &date1, &date2);
+        assert_eq!(num_days, 2);
+        assert_eq!(TrackerData::summarize_totals(date1.clone(), date2.clone(), &activities, num_days, &HashMap::new(), false), Ok(String::from(
+            "Summary from 2023-2-1 to 2023-2-2:\n\n\
+             ACTIVITY\tTOTAL TIME\tAVG TIME\tSHARE\n\
+             guitar\t\t60\t\t30\t\t3.2%\n\
+             work\t\t1800\t\t900\t\t96.8%")));
+    }
+
+    #[test]
+    fn rename_activity() {
+        // Create a TrackerData object to work with
+        let valid_json = json::object!{
+            "2023-2-1": json::object!{
+                excercise: 30,
+                exercise: 15
+            },
+            "2023-3-1": json::object!{
+                excercise: 60,
+                school: 210
+            },
+            "2023-4-1": json::object!{
+                school: 120
+            }
+        };
+        let mut tracker_data = TrackerData::new();
+        assert_eq!(tracker_data.from_json(&valid_json), Ok(()));
+
+        // Rename only within a date range
+        let date1 = Date::new_from_string("2023-3-1").unwrap();
+        let date2 = Date::new_from_string("2023-4-1").unwrap();
+        assert_eq!(tracker_data.rename_activity("excercise", "exercise", Some((date1.clone(), date2.clone()))),
+                    Ok(String::from("Renamed excercise to exercise on 1 day(s):\n\n2023-3-1: excercise 60 -> exercise 60")));
+        assert_eq!(tracker_data.data[&date1].contains_key("excercise"),
+                    false);
+
+        // Rename everywhere else, summing with the existing minutes
+        let date3 = Date::new_from_string("2023-2-1").unwrap();
+        assert_eq!(tracker_data.rename_activity("excercise", "exercise", None),
+                    Ok(String::from("Renamed excercise to exercise on 1 day(s):\n\n2023-2-1: excercise 30, exercise 15 -> exercise 45")));
+        assert_eq!(tracker_data.data[&date3]["exercise"],
+                    45);
+
+        // Try to rename an activity that no longer exists
+        assert_eq!(tracker_data.rename_activity("excercise", "exercise", None),
+                    Err(String::from("Rename error: no minutes recorded for excercise")));
+
+        // Try to rename with an invalid date range
+        assert_eq!(tracker_data.rename_activity("school", "study", Some((date2.clone(), date1.clone()))),
+                    Err(String::from("Rename error: end date 2023-3-1 is before start date 2023-4-1")));
+    }
+
+    #[test]
+    fn merge_activities() {
+        // Create a TrackerData object to work with
+        let valid_json = json::object!{
+            "2023-2-1": json::object!{
+                excercise: 30,
+                gym: 60,
+                guitar: 30
+            },
+            "2023-3-1": json::object!{
+                exercise: 45,
+                gym: 1400
+            }
+        };
+        let mut tracker_data = TrackerData::new();
+        assert_eq!(tracker_data.from_json(&valid_json), Ok(()));
+
+        // Try to merge when the total would exceed a day (nothing should change)
+        let sources = vec![String::from("excercise"), String::from("gym")];
+        assert_eq!(tracker_data.merge_activities(&sources, "exercise", None),
+                    Err(String::from("Merge error: total minutes exceeds 1440 for exercise on 2023-3-1")));
+        let date = Date::new_from_string("2023-2-1").unwrap();
+        assert_eq!(tracker_data.data[&date]["gym"],
+                    60);
+
+        // Merge within a date range
+        assert_eq!(tracker_data.merge_activities(&sources, "exercise", Some((date.clone(), date.clone()))),
+                    Ok(String::from("Merged excercise, gym into exercise on 1 day(s):\n\n2023-2-1: excercise 30, gym 60 -> exercise 90")));
+        assert_eq!(tracker_data.data[&date]["exercise"],
+                    90);
+        assert_eq!(tracker_data.data[&date]["guitar"],
+                    30);
+        assert_eq!(tracker_data.data[&date].len(),
+                    2);
+    }
+
+    #[test]
+    fn set_remove() {
+        // Create a TrackerData object to work with
+        let valid_json = json::object!{
+            "2023-2-1": json::object!{
+                guitar: 30,
+                school: 180
+            }
+        };
+        let mut tracker_data = TrackerData::new();
+        assert_eq!(tracker_data.from_json(&valid_json), Ok(()));
+
+        // Set the time for an existing activity and a new one
+        let date = Date::new_from_string("2023-2-1").unwrap();
+        assert_eq!(tracker_data.set(date.clone(), String::from("guitar"), 45), Ok(()));
+        assert_eq!(tracker_data.get(&date, "guitar"), Some(45));
+        assert_eq!(tracker_data.set(date.clone(), String::from("work"), 60), Ok(()));
+        assert_eq!(tracker_data.get(&date, "work"), Some(60));
+        assert_eq!(tracker_data.set(date.clone(), String::from("work"), 0), Ok(()));
+        assert_eq!(tracker_data.get(&date, "work"), None);
+        assert_eq!(tracker_data.set(date.clone(), String::from("work"), 1441),
+                    Err(String::from("Set error: minutes exceeds 1440 for work on 2023-2-1")));
+
+        // Remove an activity, then the whole date
+        assert_eq!(tracker_data.remove(date.clone(), Some(String::from("guitar"))), Ok(()));
+        assert_eq!(tracker_data.remove(date.clone(), Some(String::from("guitar"))),
+                    Err(String::from("Remove error: no minutes recorded for guitar on 2023-2-1")));
+        assert_eq!(tracker_data.remove(date.clone(), None), Ok(()));
+        assert_eq!(tracker_data.remove(date.clone(), None),
+                    Err(String::from("Remove error: no activities recorded for 2023-2-1")));
+    }
+
+    #[test]
+    fn diff_apply() {
+        // Create a TrackerData object and a changed copy of it
+        let valid_json = json::object!{
+            "2023-02-01": json::object!{
+                guitar: 30,
+                school: 180
+            }
+        };
+        let mut tracker_data = TrackerData::new();
+        assert_eq!(tracker_data.from_json(&valid_json), Ok(()));
+        let mut changed = tracker_data.clone();
+        let date1 = Date::new_from_string("2023-2-1").unwrap();
+        let date2 = Date::new_from_string("2023-3-1").unwrap();
+        assert_eq!(changed.remove(date1.clone(), Some(String::from("guitar"))), Ok(()));
+        assert_eq!(changed.add(date1.clone(), String::from("school"), 30), Ok(()));
+        assert_eq!(changed.add(date2.clone(), String::from("work"), 60), Ok(()));
+
+        // The diff lists every changed activity in order
+        let changes = tracker_data.diff(&changed);
+        assert_eq!(changes.iter().map(|c| (c.date.to_string(), c.activity.clone(), c.before, c.after)).collect::<Vec<_>>(),
+                    vec![(String::from("2023-2-1"), String::from("guitar"), Some(30), None),
+                         (String::from("2023-2-1"), String::from("school"), Some(180), Some(210)),
+                         (String::from("2023-3-1"), String::from("work"), None, Some(60))]);
+
+        // Applying the diff gives the changed data, and reversing it gives the original data back
+        tracker_data.apply_changes(&changes, false);
+        assert_eq!(tracker_data.to_json(), changed.to_json());
+        tracker_data.apply_changes(&changes, true);
+        assert_eq!(tracker_data.to_json(), Ok(valid_json));
+    }
+
+    #[test]
+    fn import() {
+        // Create a TrackerData object to work with
+        let valid_json = json::object!{
+            "2023-2-1": json::object!{
+                guitar: 30
+            }
+        };
+        let date = Date::new_from_string("2023-2-1").unwrap();
+        let rows = vec![(date.clone(), String::from("guitar"), 15),
+                        (date.clone(), String::from("guitar"), 10),
+                        (date.clone(), String::from("school"), 60),
+                        (date.clone(), String::from("school"), 20)];
+
+        // Each policy treats the existing guitar minutes differently (rows for new activities are summed)
+        let mut tracker_data = TrackerData::new();
+        assert_eq!(tracker_data.from_json(&valid_json), Ok(()));
+        assert_eq!(tracker_data.import(&rows, ConflictPolicy::Add), Ok(()));
+        assert_eq!((tracker_data.get(&date, "guitar"), tracker_data.get(&date, "school")), (Some(55), Some(80)));
+        let mut tracker_data = TrackerData::new();
+        assert_eq!(tracker_data.from_json(&valid_json), Ok(()));
+        assert_eq!(tracker_data.import(&rows, ConflictPolicy::Replace), Ok(()));
+        assert_eq!((tracker_data.get(&date, "guitar"), tracker_data.get(&date, "school")), (Some(25), Some(80)));
+        let mut tracker_data = TrackerData::new();
+        assert_eq!(tracker_data.from_json(&valid_json), Ok(()));
+        assert_eq!(tracker_data.import(&rows, ConflictPolicy::Skip), Ok(()));
+        assert_eq!((tracker_data.get(&date, "guitar"), tracker_data.get(&date, "school")), (Some(30), Some(80)));
+
+        // A failed import does not change anything
+        let too_much = vec![(date.clone(), String::from("school"), 60), (date.clone(), String::from("guitar"), 1440)];
+        assert_eq!(tracker_data.import(&too_much, ConflictPolicy::Add),
+                    Err(String::from("Add error: total minutes exceeds 1440 for guitar on 2023-2-1")));
+        assert_eq!(tracker_data.get(&date, "school"), Some(80));
+        let new_too_much = vec![(date.clone(), String::from("reading"), 1500)];
+        assert_eq!(tracker_data.import(&new_too_much, ConflictPolicy::Add),
+                    Err(String::from("Import error: minutes exceeds 1440 for reading on 2023-2-1")));
+        assert_eq!(tracker_data.get(&date, "reading"), None);
+        assert_eq!(ConflictPolicy::new_from_string("max"),
+                    Err(String::from("Conflict policy error: \"max\" is not one of add, replace, skip")));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,155 @@
+use crate::charts::period_totals;
+use crate::date::Date;
+use crate::goals::Period;
+use crate::tracker_data::TrackerData;
+
+// The windows (in days) of the moving averages in a trend report
+pub const WINDOWS: [usize; 2] = [7, 30];
+
+// How many standard deviations from the average a day has to be to count as unusual
+pub const UNUSUAL_DEVIATIONS: f64 = 2.0;
+
+// The change in minutes per day each week below which a trend counts as steady
+const STEADY_SLOPE: f64 = 0.5;
+
+// Returns the average of each window of values ending at every value (None until a full window of values is available)
+pub fn moving_averages(values: &[u32], window: usize) -> Vec<Option<f64>> {
+    (0..values.len()).map(|i| {
+        if window == 0 || i + 1 < window {
+            None
+        } else {
+            Some(values[i + 1 - window..=i].iter().sum::<u32>() as f64 / window as f64)
+        }
+    }).collect()
+}
+
+// Returns the slope of the least-squares line through the values (one per day), in minutes per day per day
+// (None if there are fewer than two values)
+pub fn slope(values: &[u32]) -> Option<f64> {
+    let n = values.len() as f64;
+    if values.len() < 2 {
+        return None;
+    }
+    let mean_x = (n - 1.0) / 2.0;
+    let mean_y = values.iter().sum::<u32>() as f64 / n;
+    let mut covariance = 0.0;
+    let mut variance = 0.0;
+    for (i, value) in values.iter().enumerate() {
+        covariance += (i as f64 - mean_x) * (*value as f64 - mean_y);
+        variance += (i as f64 - mean_x).powi(2);
+    }
+    Some(covariance / variance)
+}
+
+// Returns the number of standard deviations each value is from the average of the values (None if they are all the same)
+pub fn deviations(values: &[u32]) -> Option<Vec<f64>> {
+    if values.is_empty() {
+        return None;
+    }
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<u32>() as f64 / n;
+    let std_dev = (values.iter().map(|v| (*v as f64 - mean).powi(2)).sum::<f64>() / n).sqrt();
+    if std_dev == 0.0 {
+        return None;
+    }
+    Some(values.iter().map(|v| (*v as f64 - mean) / std_dev).collect())
+}
+
+// Returns a report (as a String) of the trend of an activity from start_date to end_date: the average, the slope of
+// the least-squares line, and every day with its minutes, moving averages, and whether it was unusual
+// The moving averages include the days before start_date, so that they are available from the first day
+pub fn trend_report(tracker_data: &TrackerData, activity: &str, start_date: &Date, end_date: &Date) -> Result<String, String> {
+    if end_date < start_date {
+        return Err(format!("Trend error: end date {} is before start date {}", end_date.to_string(), start_date.to_string()));
+    }
+    let longest_window = WINDOWS.iter().copied().max().unwrap_or(1);
+    let lead_start = start_date.sub_days(longest_window as u16 - 1).unwrap_or(start_date.clone());
+    let days = period_totals(tracker_data, activity, &lead_start, end_date, Period::Day)?;
+    let lead_in = days.iter().position(|(date, _, _)| date == start_date).unwrap_or(0);
+    let all_values: Vec<u32> = days.iter().map(|(_, _, minutes)| *minutes).collect();
+    let values = &all_values[lead_in..];
+    let averages: Vec<Vec<Option<f64>>> = WINDOWS.iter().map(|w| moving_averages(&all_values, *w)).collect();
+
+    let mut report = format!("Trend of {} from {} to {} ({} days):\n\n", activity, start_date.to_string(), end_date.to_string(), values.len());
+    let average = values.iter().sum::<u32>() as f64 / values.len() as f64;
+    report.push_str(&format!("Average: {:.1} minutes per day\n", average));
+    match slope(values) {
+        Some(s) => {
+            let weekly = s * 7.0;
+            let direction = if weekly.abs() < STEADY_SLOPE {"steady"} else if weekly > 0.0 {"growing"} else {"fading"};
+            report.push_str(&format!("Slope: {:+.1} minutes per day each week ({})\n", weekly, direction));
+        }
+        None => report.push_str("Slope: not enough days\n"),
+    }
+    let deviations = deviations(values);
+    let unusual = deviations.as_ref().map_or(0, |d| d.iter().filter(|z| z.abs() >= UNUSUAL_DEVIATIONS).count());
+    report.push_str(&format!("Unusual days: {} (at least {} standard deviations from the average)\n\n", unusual, UNUSUAL_DEVIATIONS));
+
+    // Every day gets a row, with a note if it was unusual
+    report.push_str("DATE\t\tMINUTES");
+    for window in WINDOWS {
+        report.push_str(&format!("\t{}-DAY", window));
+    }
+    report.push_str("\tNOTE\n");
+    for (i, (date, _, minutes)) in days.iter().enumerate().skip(lead_in) {
+        let date_str = date.to_string();
+        let date_tab = if date_str.len() < 8 {"\t\t"} else {"\t"};
+        let mut line = format!("{}{}{}", date_str, date_tab, minutes);
+        for window_averages in &averages {
+            match window_averages[i] {
+                Some(a) => line.push_str(&format!("\t{:.1}", a)),
+                None => line.push_str("\t-"),
+            }
+        }
+        let note = match deviations.as_ref().map(|d| d[i - lead_in]) {
+            Some(z) if z >= UNUSUAL_DEVIATIONS => "unusually high",
+            Some(z) if z <= -UNUSUAL_DEVIATIONS => "unusually low",
+            _ => "",
+        };
+        line.push_str(&format!("\t{}", note));
+        report.push_str(line.trim_end());
+        report.push('\n');
+    }
+    Ok(report.trim_end_matches("\n").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trend::{moving_averages, slope, deviations, trend_report};
+    use crate::tracker_data::TrackerData;
+    use crate::date::Date;
+
+    #[test]
+    fn statistics() {
+        assert_eq!(moving_averages(&[10, 20, 30, 40], 2), vec![None, Some(15.0), Some(25.0), Some(35.0)]);
+        assert_eq!(slope(&[10, 20, 30, 40]), Some(10.0));
+        assert_eq!(slope(&[30, 30, 30]), Some(0.0));
+        assert_eq!(slope(&[30]), None);
+        assert_eq!(deviations(&[10, 10, 10]), None);
+        assert_eq!(deviations(&[10, 30]), Some(vec![-1.0, 1.0]));
+    }
+
+    #[test]
+    fn report() {
+        // Guitar grows by 5 minutes a day, with one unusually long day
+        let mut tracker_data = TrackerData::new();
+        for (date, minutes) in [("2023-5-31", 70), ("2023-6-1", 10), ("2023-6-2", 15), ("2023-6-3", 20), ("2023-6-4", 25),
+                                ("2023-6-5", 30), ("2023-6-6", 35), ("2023-6-7", 40), ("2023-6-8", 45), ("2023-6-9", 50),
+                                ("2023-6-10", 55), ("2023-6-11", 240), ("2023-6-12", 65), ("2023-6-13", 70)] {
+            assert_eq!(tracker_data.add(Date::new_from_string(date).unwrap(), String::from("guitar"), minutes), Ok(()));
+        }
+        let start_date = Date::new_from_string("2023-6-1").unwrap();
+        let end_date = Date::new_from_string("2023-6-13").unwrap();
+        let report = trend_report(&tracker_data, "guitar", &start_date, &end_date).unwrap();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines[0], "Trend of guitar from 2023-6-1 to 2023-6-13 (13 days):");
+        assert_eq!(lines[2], "Average: 53.8 minutes per day");
+        assert_eq!(lines[3], "Slope: +62.7 minutes per day each week (growing)");
+        assert_eq!(lines[4], "Unusual days: 1 (at least 2 standard deviations from the average)");
+        assert_eq!(lines[6], "DATE\t\tMINUTES\t7-DAY\t30-DAY\tNOTE");
+        // The 7-day average on the first day includes 2023-5-31, from before the range
+        assert_eq!(lines[7], "2023-6-1\t10\t11.4\t2.7");
+        assert_eq!(lines[17], "2023-6-11\t240\t70.7\t21.2\tunusually high");
+        assert!(trend_report(&tracker_data, "guitar", &end_date, &start_date).is_err());
+    }
+}
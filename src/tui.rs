@@ -0,0 +1,487 @@
+use std::io::{self, Write};
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+
+use crate::activity_registry::ActivityRegistry;
+use crate::date::Date;
+use crate::goals::Period;
+use crate::heatmap::{MONTH_NAMES, WEEKDAY_NAMES};
+use crate::tracker_data::TrackerData;
+
+// The widths of the calendar, day, and week panes, and the space between them
+const CALENDAR_WIDTH: usize = 21;
+const DAY_WIDTH: usize = 28;
+const WEEK_WIDTH: usize = 24;
+const PANE_GAP: usize = 3;
+
+// The rows above the panes (the title and a blank line) and below them (the message and the prompt or key help)
+const HEADER_ROWS: usize = 2;
+const FOOTER_ROWS: usize = 2;
+
+// The keys shown at the bottom of the screen when not asking for anything
+const KEY_HELP: &str = "←/→ day  ↑/↓ activity  [/] week  t today  a add  s sub  e set  n new  d rm  q save and quit  Q quit";
+
+// The lines of a pane, along with the row and column (within the pane) of what is selected in it, if anything
+type Pane = (Vec<String>, Option<(usize, usize)>);
+
+// Enum Definition
+// The change that a prompt is asking for
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Action {
+    Add,
+    Subtract,
+    Set,
+    NewActivity,
+}
+
+// Struct Definition
+// What is being typed at the bottom of the screen: the minutes to add, subtract, or set for an activity, or the name
+// of an activity to add time to
+pub struct Prompt {
+    pub action: Action,
+    pub activity: String,
+    pub input: String,
+}
+
+// Struct Definition
+// What the screen shows as lines of text, along with the cells (row, column, and length, in characters) drawn in
+// reverse video to show what is selected
+pub struct Screen {
+    pub lines: Vec<String>,
+    pub highlights: Vec<(usize, usize, usize)>,
+}
+
+// Struct Definition
+// The state of the full-screen interface: the date being viewed, the activity selected on that date, any prompt being
+// typed into, the message from the last change, and whether to quit (and if so, whether to save)
+pub struct Tui {
+    pub date: Date,
+    pub selected: usize,
+    pub prompt: Option<Prompt>,
+    pub message: String,
+    pub quit: Option<bool>,
+    today: Date,
+}
+
+// Public Methods
+impl Tui {
+    // Creates a new Tui viewing today
+    pub fn new(today: Date) -> Tui {
+        Tui {
+            date: today.clone(),
+            selected: 0,
+            prompt: None,
+            message: String::new(),
+            quit: None,
+            today,
+        }
+    }
+
+    // Returns the activities with time on the date being viewed, with their minutes
+    pub fn day_activities(self: &Self, tracker_data: &TrackerData) -> Vec<(String, u32)> {
+        self.activities_on(tracker_data, &self.date)
+    }
+
+    // Moves around or changes the tracker data (using the same TrackerData methods as the add, sub, set, and rm
+    // functions) in response to a key
+    pub fn handle_key(self: &mut Self, key: KeyEvent, tracker_data: &mut TrackerData, registry: &mut ActivityRegistry) {
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = Some(false);
+            return;
+        }
+        match self.prompt.take() {
+            Some(prompt) => self.handle_prompt_key(key.code, prompt, tracker_data, registry),
+            None => self.handle_browse_key(key.code, tracker_data),
+        }
+    }
+
+    // Returns the screen (of width columns and height rows) showing a calendar of the month, the activities on the date
+    // being viewed, and a summary of the week, followed by the message and the prompt (or the keys)
+    pub fn render(self: &Self, tracker_data: &TrackerData, width: usize, height: usize) -> Result<Screen, String> {
+        let mut highlights: Vec<(usize, usize, usize)> = Vec::new();
+        let (calendar, calendar_highlight) = self.calendar_pane(tracker_data)?;
+        let (day, day_highlight) = self.day_pane(tracker_data);
+        let (week, week_highlight) = self.week_pane(tracker_data)?;
+        let day_column = CALENDAR_WIDTH + PANE_GAP;
+        let week_column = day_column + DAY_WIDTH + PANE_GAP;
+        for (pane_highlight, column, pane_width) in [(calendar_highlight, 0, 2), (day_highlight, day_column, DAY_WIDTH),
+                                                     (week_highlight, week_column, WEEK_WIDTH)] {
+            if let Some((row, offset)) = pane_highlight {
+                highlights.push((HEADER_ROWS + row, column + offset, pane_width));
+            }
+        }
+
+        let title = format!("tracker - {} {}", WEEKDAY_NAMES[self.date.weekday() as usize], self.date.to_string());
+        let mut lines = vec![title, String::new()];
+        let pane_rows = calendar.len().max(day.len()).max(week.len()).min(height.saturating_sub(HEADER_ROWS + FOOTER_ROWS));
+        for row in 0..pane_rows {
+            let cell = |pane: &Vec<String>, pane_width: usize| fit(pane.get(row).map_or("", |l| l.as_str()), pane_width);
+            let line = format!("{:<calendar$}{:gap$}{:<day$}{:gap$}{}", cell(&calendar, CALENDAR_WIDTH), "",
+                               cell(&day, DAY_WIDTH), "", cell(&week, WEEK_WIDTH),
+                               calendar = CALENDAR_WIDTH, day = DAY_WIDTH, gap = PANE_GAP);
+            lines.push(line.trim_end().to_string());
+        }
+        while lines.len() + FOOTER_ROWS < height {
+            lines.push(String::new());
+        }
+        lines.push(self.message.clone());
+        lines.push(match &self.prompt {
+            Some(prompt) => format!("{}{}", prompt.question(), prompt.input),
+            None => String::from(KEY_HELP),
+        });
+        let lines: Vec<String> = lines.iter().map(|l| fit(l, width)).collect();
+        highlights.retain(|(row, column, _)| *row < HEADER_ROWS + pane_rows && *column < width);
+        Ok(Screen {lines, highlights})
+    }
+
+    // Runs the full-screen interface in the terminal until it is quit, returning whether to save the changes
+    pub fn run(self: &mut Self, tracker_data: &mut TrackerData, registry: &mut ActivityRegistry) -> Result<bool, String> {
+        let mut stdout = io::stdout();
+        terminal::enable_raw_mode().map_err(tui_error)?;
+        execute!(stdout, EnterAlternateScreen, Hide).map_err(tui_error)?;
+        let result = self.event_loop(&mut stdout, tracker_data, registry);
+        // Put the terminal back the way it was, even if drawing failed
+        execute!(stdout, Show, LeaveAlternateScreen).ok();
+        terminal::disable_raw_mode().ok();
+        result
+    }
+}
+
+// Private Methods
+impl Tui {
+    // Draws the screen and handles keys until quit
+    fn event_loop(self: &mut Self, stdout: &mut io::Stdout, tracker_data: &mut TrackerData, registry: &mut ActivityRegistry)
+                  -> Result<bool, String> {
+        loop {
+            let (width, height) = terminal::size().map_err(tui_error)?;
+            let screen = self.render(tracker_data, width as usize, height as usize)?;
+            queue!(stdout, Clear(ClearType::All)).map_err(tui_error)?;
+            for (row, line) in screen.lines.iter().enumerate() {
+                queue!(stdout, MoveTo(0, row as u16), Print(line)).map_err(tui_error)?;
+            }
+            for (row, column, length) in screen.highlights {
+                let text: String = screen.lines[row].chars().chain(std::iter::repeat(' ')).skip(column).take(length).collect();
+                queue!(stdout, MoveTo(column as u16, row as u16), SetAttribute(Attribute::Reverse), Print(text),
+                       SetAttribute(Attribute::Reset)).map_err(tui_error)?;
+            }
+            stdout.flush().map_err(tui_error)?;
+
+            if let Event::Key(key) = event::read().map_err(tui_error)? {
+                self.handle_key(key, tracker_data, registry);
+            }
+            if let Some(save) = self.quit {
+                return Ok(save);
+            }
+        }
+    }
+
+    // Moves around, or starts a change, in response to a key when nothing is being typed
+    fn handle_browse_key(self: &mut Self, code: KeyCode, tracker_data: &mut TrackerData) {
+        let num_activities = self.day_activities(tracker_data).len();
+        let selected_activity = self.day_activities(tracker_data).get(self.selected).map(|(a, _)| a.clone());
+        match code {
+            KeyCode::Left | KeyCode::Char('h') => self.move_days(-1),
+            KeyCode::Right | KeyCode::Char('l') => self.move_days(1),
+            KeyCode::Char('[') | KeyCode::PageUp => self.move_days(-7),
+            KeyCode::Char(']') | KeyCode::PageDown => self.move_days(7),
+            KeyCode::Char('t') | KeyCode::Home => {
+                self.date = self.today.clone();
+                self.selected = 0;
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.selected = (self.selected + 1).min(num_activities.saturating_sub(1)),
+            KeyCode::Char('a') | KeyCode::Char('s') | KeyCode::Char('e') | KeyCode::Enter => {
+                let action = match code {
+                    KeyCode::Char('a') => Action::Add,
+                    KeyCode::Char('s') => Action::Subtract,
+                    _ => Action::Set,
+                };
+                match selected_activity {
+                    Some(activity) => self.prompt = Some(Prompt {action, activity, input: String::new()}),
+                    None => self.message = String::from("No activity on this day (use n to add time to one)"),
+                }
+            }
+            KeyCode::Char('n') => self.prompt = Some(Prompt {action: Action::NewActivity, activity: String::new(), input: String::new()}),
+            KeyCode::Char('d') | KeyCode::Delete => {
+                if let Some(activity) = selected_activity {
+                    let description = format!("rm {} on {}", activity, self.date.to_string());
+                    self.report(description, tracker_data.remove(self.date.clone(), Some(activity)));
+                    self.selected = self.selected.min(num_activities.saturating_sub(2));
+                }
+            }
+            KeyCode::Char('q') => self.quit = Some(true),
+            KeyCode::Char('Q') => self.quit = Some(false),
+            _ => {}
+        }
+    }
+
+    // Types into the prompt, cancels it, or makes the change it asks for
+    fn handle_prompt_key(self: &mut Self, code: KeyCode, mut prompt: Prompt, tracker_data: &mut TrackerData,
+                         registry: &mut ActivityRegistry) {
+        match code {
+            KeyCode::Esc => self.message = String::from("Cancelled"),
+            KeyCode::Backspace => {
+                prompt.input.pop();
+                self.prompt = Some(prompt);
+            }
+            KeyCode::Char(c) if prompt.action == Action::NewActivity || c.is_ascii_digit() => {
+                prompt.input.push(c);
+                self.prompt = Some(prompt);
+            }
+            KeyCode::Enter if prompt.input.is_empty() => self.message = String::from("Cancelled"),
+            KeyCode::Enter if prompt.action == Action::NewActivity => {
+                // A new activity is created only when asked for, just like with the add function
+                let (name, allow_new) = match prompt.input.trim().strip_suffix("--new") {
+                    Some(name) => (name.trim(), true),
+                    None => (prompt.input.trim(), false),
+                };
+                match registry.check(name, allow_new) {
+                    Ok(activity) => self.prompt = Some(Prompt {action: Action::Add, activity, input: String::new()}),
+                    Err(e) => self.message = e,
+                }
+            }
+            KeyCode::Enter => {
+                let minutes = match prompt.input.parse::<u16>() {
+                    Ok(m) => m,
+                    Err(_) => {
+                        self.message = format!("Parse arguments error: \"{}\" cannot be interpreted as an integer", prompt.input);
+                        return;
+                    }
+                };
+                let (date, activity) = (self.date.clone(), prompt.activity.clone());
+                let (description, result) = match prompt.action {
+                    Action::Subtract => (format!("sub {} {} on {}", minutes, activity, date.to_string()),
+                                         tracker_data.subtract(date, activity.clone(), minutes)),
+                    Action::Set => (format!("set {} to {} on {}", activity, minutes, date.to_string()),
+                                    tracker_data.set(date, activity.clone(), minutes)),
+                    _ => (format!("add {} {} on {}", minutes, activity, date.to_string()),
+                          tracker_data.add(date, activity.clone(), minutes)),
+                };
+                self.report(description, result);
+                // Keep the changed activity selected (or the one now in its place, if it was subtracted away)
+                let activities = self.day_activities(tracker_data);
+                self.selected = match activities.iter().position(|(a, _)| *a == activity) {
+                    Some(i) => i,
+                    None => self.selected.min(activities.len().saturating_sub(1)),
+                };
+            }
+            _ => self.prompt = Some(prompt),
+        }
+    }
+
+    // Shows the description of a change if it was made, or else why it was not
+    fn report(self: &mut Self, description: String, result: Result<(), String>) {
+        self.message = match result {
+            Ok(_) => description,
+            Err(e) => e,
+        };
+    }
+
+    // Moves the date being viewed by a number of days (staying put if that would leave the dates that can be stored)
+    fn move_days(self: &mut Self, days: i32) {
+        let moved = if days < 0 {
+            self.date.sub_days(days.unsigned_abs() as u16)
+        } else {
+            self.date.add_days(days as u16)
+        };
+        if let Ok(date) = moved {
+            self.date = date;
+            self.selected = 0;
+        }
+    }
+
+    // Returns the lines of a calendar of the month being viewed (with a mark after each day with time), along with the
+    // row and column of the date being viewed
+    fn calendar_pane(self: &Self, tracker_data: &TrackerData) -> Result<Pane, String> {
+        let (first, last) = Period::Month.bounds(&self.date)?;
+        let (year, month, _) = first.to_tuple();
+        let mut lines = vec![format!("{:^20}", format!("{} {}", MONTH_NAMES[month as usize - 1], year)),
+                             WEEKDAY_NAMES.iter().map(|name| &name[..2]).collect::<Vec<&str>>().join(" ")];
+        let mut highlight = None;
+        let mut week = " ".repeat(3 * first.weekday() as usize);
+        let mut date = first;
+        loop {
+            if date == self.date {
+                highlight = Some((lines.len(), week.len()));
+            }
+            let (_, _, day) = date.to_tuple();
+            let has_time = !self.activities_on(tracker_data, &date).is_empty();
+            week.push_str(&format!("{:>2}{}", day, if has_time {"*"} else {" "}));
+            if date.weekday() == 6 || date == last {
+                lines.push(week.trim_end().to_string());
+                week = String::new();
+            }
+            if date == last {
+                break;
+            }
+            date = date.add_days(1)?;
+        }
+        Ok((lines, highlight))
+    }
+
+    // Returns the lines listing the activities on the date being viewed with their minutes, along with the row and
+    // column of the selected activity
+    fn day_pane(self: &Self, tracker_data: &TrackerData) -> Pane {
+        let activities = self.day_activities(tracker_data);
+        let mut lines = vec![String::from("DAY"), String::new()];
+        if activities.is_empty() {
+            lines.push(String::from("No time on this day"));
+            return (lines, None);
+        }
+        let highlight = Some((lines.len() + self.selected.min(activities.len() - 1), 0));
+        for (activity, minutes) in &activities {
+            lines.push(minutes_row(activity, *minutes, DAY_WIDTH));
+        }
+        lines.push(minutes_row("TOTAL", activities.iter().map(|(_, m)| m).sum(), DAY_WIDTH));
+        (lines, highlight)
+    }
+
+    // Returns the lines summarizing the week being viewed: the total of each activity, then the total of each day,
+    // along with the row and column of the date being viewed
+    fn week_pane(self: &Self, tracker_data: &TrackerData) -> Result<Pane, String> {
+        let (start, end) = Period::Week.bounds(&self.date)?;
+        let (totals, _) = tracker_data.activity_totals(&start, &end);
+        let mut lines = vec![format!("WEEK OF {}", start.to_string()), String::new()];
+        for (activity, minutes) in &totals {
+            lines.push(minutes_row(activity, *minutes, WEEK_WIDTH));
+        }
+        lines.push(minutes_row("TOTAL", totals.values().sum(), WEEK_WIDTH));
+        lines.push(String::new());
+        let mut highlight = None;
+        let mut date = start;
+        loop {
+            if date == self.date {
+                highlight = Some((lines.len(), 0));
+            }
+            let minutes = self.activities_on(tracker_data, &date).iter().map(|(_, m)| m).sum();
+            lines.push(minutes_row(WEEKDAY_NAMES[date.weekday() as usize], minutes, WEEK_WIDTH));
+            if date == end {
+                break;
+            }
+            date = date.add_days(1)?;
+        }
+        Ok((lines, highlight))
+    }
+
+    // Returns the activities with time on a date, with their minutes
+    fn activities_on(self: &Self, tracker_data: &TrackerData, date: &Date) -> Vec<(String, u32)> {
+        tracker_data.activity_totals(date, date).0.into_iter().collect()
+    }
+}
+
+// Private Methods
+impl Prompt {
+    // Returns the question asked at the start of the prompt
+    fn question(self: &Self) -> String {
+        match self.action {
+            Action::Add => format!("Add minutes to {}: ", self.activity),
+            Action::Subtract => format!("Subtract minutes from {}: ", self.activity),
+            Action::Set => format!("Set minutes of {} to: ", self.activity),
+            Action::NewActivity => String::from("Add time to activity (add --new to create it): "),
+        }
+    }
+}
+
+// Returns a line of width columns with a name on the left and minutes on the right
+fn minutes_row(name: &str, minutes: u32, width: usize) -> String {
+    let minutes = minutes.to_string();
+    let name_width = width.saturating_sub(minutes.len() + 1);
+    format!("{:<name_width$} {}", fit(name, name_width), minutes)
+}
+
+// Returns text cut off after width characters
+fn fit(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+// Returns the error message for a terminal error
+fn tui_error(error: io::Error) -> String {
+    format!("TUI error: {}", error)
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use crate::tui::Tui;
+    use crate::activity_registry::ActivityRegistry;
+    use crate::tracker_data::TrackerData;
+    use crate::date::Date;
+
+    fn date(date_str: &str) -> Date {
+        Date::new_from_string(date_str).unwrap()
+    }
+
+    fn press(tui: &mut Tui, keys: &[KeyCode], tracker_data: &mut TrackerData, registry: &mut ActivityRegistry) {
+        for key in keys {
+            tui.handle_key(KeyEvent::new(*key, KeyModifiers::NONE), tracker_data, registry);
+        }
+    }
+
+    #[test]
+    fn editing() {
+        let mut tracker_data = TrackerData::new();
+        assert_eq!(tracker_data.add(date("2023-6-7"), String::from("guitar"), 30), Ok(()));
+        assert_eq!(tracker_data.add(date("2023-6-7"), String::from("school"), 120), Ok(()));
+        let mut registry = ActivityRegistry::new();
+        registry.register_from(&tracker_data);
+        let mut tui = Tui::new(date("2023-6-7"));
+
+        // Add to the second activity, then set the first
+        press(&mut tui, &[KeyCode::Down, KeyCode::Char('a'), KeyCode::Char('1'), KeyCode::Char('5'), KeyCode::Enter],
+              &mut tracker_data, &mut registry);
+        assert_eq!(tracker_data.get(&date("2023-6-7"), "school"), Some(135));
+        assert_eq!(tui.message, String::from("add 15 school on 2023-6-7"));
+        press(&mut tui, &[KeyCode::Up, KeyCode::Char('e'), KeyCode::Char('x'), KeyCode::Char('4'), KeyCode::Char('5'), KeyCode::Enter],
+              &mut tracker_data, &mut registry);
+        assert_eq!(tracker_data.get(&date("2023-6-7"), "guitar"), Some(45));
+
+        // Time can only be added to a new activity when asked for
+        let keys: Vec<KeyCode> = "npiano".chars().map(KeyCode::Char).chain([KeyCode::Enter]).collect();
+        press(&mut tui, &keys, &mut tracker_data, &mut registry);
+        assert_eq!(tui.message, String::from("Activity error: unknown activity \"piano\" (use \"--new\" to create it)"));
+        let keys: Vec<KeyCode> = "npiano --new".chars().map(KeyCode::Char).chain([KeyCode::Enter, KeyCode::Char('2'), KeyCode::Char('0'), KeyCode::Enter]).collect();
+        press(&mut tui, &keys, &mut tracker_data, &mut registry);
+        assert_eq!(tracker_data.get(&date("2023-6-7"), "piano"), Some(20));
+        assert_eq!(tui.selected, 1);
+
+        // Remove the selected activity, cancel a prompt, and move to another day
+        press(&mut tui, &[KeyCode::Char('d'), KeyCode::Char('s'), KeyCode::Char('9'), KeyCode::Esc, KeyCode::Right],
+              &mut tracker_data, &mut registry);
+        assert_eq!(tracker_data.get(&date("2023-6-7"), "piano"), None);
+        assert_eq!(tracker_data.get(&date("2023-6-7"), "school"), Some(135));
+        assert_eq!(tui.date, date("2023-6-8"));
+        press(&mut tui, &[KeyCode::Char('a')], &mut tracker_data, &mut registry);
+        assert_eq!(tui.message, String::from("No activity on this day (use n to add time to one)"));
+        press(&mut tui, &[KeyCode::Char('q')], &mut tracker_data, &mut registry);
+        assert_eq!(tui.quit, Some(true));
+    }
+
+    #[test]
+    fn rendering() {
+        let mut tracker_data = TrackerData::new();
+        assert_eq!(tracker_data.add(date("2023-6-5"), String::from("guitar"), 30), Ok(()));
+        assert_eq!(tracker_data.add(date("2023-6-7"), String::from("guitar"), 45), Ok(()));
+        assert_eq!(tracker_data.add(date("2023-6-7"), String::from("school"), 120), Ok(()));
+        let mut tui = Tui::new(date("2023-6-7"));
+        tui.selected = 1;
+        let screen = tui.render(&tracker_data, 100, 20).unwrap();
+        assert_eq!(screen.lines.len(), 20);
+        assert_eq!(screen.lines[0], "tracker - Wed 2023-6-7");
+        assert_eq!(screen.lines[2], "      Jun 2023          DAY                            WEEK OF 2023-6-5");
+        assert_eq!(screen.lines[3], "Mo Tu We Th Fr Sa Su");
+        assert_eq!(screen.lines[4], "          1  2  3  4    guitar                    45   guitar                75");
+        assert_eq!(screen.lines[5], " 5* 6  7* 8  9 10 11    school                   120   school               120");
+        assert_eq!(screen.lines[6], "12 13 14 15 16 17 18    TOTAL                    165   TOTAL                195");
+        assert_eq!(screen.lines[10], "                                                       Wed                  165");
+        // The date being viewed is highlighted in the calendar and the week, and the selected activity in the day
+        assert_eq!(screen.highlights, vec![(5, 6, 2), (5, 24, 28), (10, 55, 24)]);
+        assert!(screen.lines[19].starts_with("←/→ day"));
+    }
+}